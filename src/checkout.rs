@@ -0,0 +1,329 @@
+//! Updating the index and files of a worktree whose checked out ref we move,
+//! modeled on `git reset --keep`, `--merge`, and `--hard`.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+use bstr::{BString, ByteSlice};
+use clap::ValueEnum;
+use gix::index::entry::Stage;
+use gix::objs::tree::EntryMode;
+use gix::status::index_worktree::iter::Summary;
+use gix::status::UntrackedFiles;
+use gix::{ObjectId, Repository};
+use miette::{Context, IntoDiagnostic};
+use tap::TapFallible;
+
+#[allow(unused)]
+use log::{trace, debug, warn, info, error};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(ValueEnum)]
+pub enum UpdateMode
+{
+	/// Like `git reset --keep`: update files that differ between the old and new commit,
+	/// refusing if any of them have local changes.
+	Keep,
+
+	/// Like `git reset --merge`: also reset staged changes, but refuse if any file we'd
+	/// touch has unstaged changes.
+	Merge,
+
+	/// Like `git reset --hard`: discard all local changes to tracked files.
+	Hard,
+}
+
+impl UpdateMode
+{
+	pub fn name(self) -> &'static str
+	{
+		use UpdateMode::*;
+		match self {
+			Keep => "keep",
+			Merge => "merge",
+			Hard => "hard",
+		}
+	}
+}
+
+/// Local modifications in a worktree, relative to its checked out commit.
+#[derive(Debug, Clone, Default)]
+struct LocalChanges
+{
+	/// Paths with changes between the commit and the index.
+	staged: BTreeSet<BString>,
+
+	/// Paths with changes between the index and the files on disk.
+	unstaged: BTreeSet<BString>,
+
+	/// Files on disk git doesn't know about.
+	untracked: BTreeSet<BString>,
+}
+
+impl LocalChanges
+{
+	fn read(repo: &Repository, head_tree: ObjectId) -> miette::Result<Self>
+	{
+		let mut changes = Self::default();
+
+		let status_iter = repo
+			.status(gix::progress::Discard)
+			.into_diagnostic()?
+			.untracked_files(UntrackedFiles::Files)
+			.index_worktree_rewrites(None)
+			.tree_index_track_renames(gix::status::tree_index::TrackRenames::Disabled)
+			.head_tree(head_tree)
+			.into_iter(None)
+			.into_diagnostic()?;
+
+		for item in status_iter {
+			match item.into_diagnostic()? {
+				gix::status::Item::TreeIndex(change) => {
+					changes.staged.insert(change.fields().0.to_owned());
+				},
+				gix::status::Item::IndexWorktree(item) => match item.summary() {
+					Some(Summary::Added) => {
+						changes.untracked.insert(item.rela_path().to_owned());
+					},
+					Some(_) => {
+						changes.unstaged.insert(item.rela_path().to_owned());
+					},
+					None => (),
+				},
+			}
+		}
+
+		Ok(changes)
+	}
+}
+
+/// Every non-tree entry in tree `tree_id`, recursively, by path.
+fn tree_entries(repo: &Repository, tree_id: ObjectId) -> miette::Result<BTreeMap<BString, (EntryMode, ObjectId)>>
+{
+	let tree = repo
+		.find_tree(tree_id)
+		.into_diagnostic()?;
+
+	let entries = tree
+		.traverse()
+		.breadthfirst
+		.files()
+		.into_diagnostic()
+		.with_context(|| format!("while reading tree {}", tree_id))?;
+
+	Ok(entries
+		.into_iter()
+		.filter(|entry| !entry.mode.is_tree())
+		.map(|entry| (entry.filepath, (entry.mode, entry.oid)))
+		.collect())
+}
+
+fn tree_of(repo: &Repository, commit: ObjectId) -> miette::Result<ObjectId>
+{
+	let tree_id = repo
+		.find_commit(commit)
+		.into_diagnostic()?
+		.tree_id()
+		.into_diagnostic()
+		.with_context(|| format!("while finding tree of commit {}", commit))?;
+
+	Ok(tree_id.detach())
+}
+
+/// What we will do to one worktree.
+pub struct WorktreeUpdate
+{
+	repo: Repository,
+	mode: UpdateMode,
+	new_tree: ObjectId,
+
+	/// Files to write from the new tree.
+	write: BTreeSet<BString>,
+
+	/// Files to delete, which aren't in the new tree.
+	remove: BTreeSet<BString>,
+
+	/// Files with local changes that this update would lose.
+	/// The update must not be applied if this isn't empty.
+	pub lost: Vec<BString>,
+}
+
+impl WorktreeUpdate
+{
+	/// Works out what needs to change in `repo`'s worktree to move it from `old_commit` to `new_commit`.
+	pub fn plan(repo: Repository, old_commit: ObjectId, new_commit: ObjectId, mode: UpdateMode) -> miette::Result<Self>
+	{
+		let old_tree = tree_of(&repo, old_commit)?;
+		let new_tree = tree_of(&repo, new_commit)?;
+
+		let old_entries = tree_entries(&repo, old_tree)?;
+		let new_entries = tree_entries(&repo, new_tree)?;
+
+		let changed: BTreeSet<BString> = old_entries
+			.keys()
+			.chain(new_entries.keys())
+			.filter(|path| old_entries.get(*path) != new_entries.get(*path))
+			.cloned()
+			.collect();
+
+		let local = LocalChanges::read(&repo, old_tree)
+			.with_context(|| format!("while checking for local changes in {}", display_dir(&repo).display()))?;
+
+		debug!("{} files change, local changes: {:?}", changed.len(), local);
+
+		let (lost, touched): (BTreeSet<&BString>, BTreeSet<&BString>) = match mode {
+			UpdateMode::Keep => {
+				let dirty: BTreeSet<&BString> = local.staged
+					.iter()
+					.chain(&local.unstaged)
+					.chain(&local.untracked)
+					.collect();
+				let lost = changed.iter().filter(|path| dirty.contains(path)).collect();
+				(lost, changed.iter().collect())
+			},
+			UpdateMode::Merge => {
+				let touched: BTreeSet<&BString> = changed.iter().chain(&local.staged).collect();
+				let lost = touched
+					.iter()
+					.copied()
+					.filter(|path| local.unstaged.contains(*path))
+					.chain(changed.iter().filter(|path| local.untracked.contains(*path)))
+					.collect();
+				(lost, touched)
+			},
+			UpdateMode::Hard => {
+				let touched = changed
+					.iter()
+					.chain(&local.staged)
+					.chain(&local.unstaged)
+					.collect();
+				(BTreeSet::new(), touched)
+			},
+		};
+
+		let (write, remove): (BTreeSet<BString>, BTreeSet<BString>) = touched
+			.into_iter()
+			.cloned()
+			.partition(|path| new_entries.contains_key(path));
+
+		Ok(Self {
+			lost: lost.into_iter().cloned().collect(),
+			repo,
+			mode,
+			new_tree,
+			write,
+			remove,
+		})
+	}
+
+	pub fn mode(&self) -> UpdateMode
+	{
+		self.mode
+	}
+
+	/// The directory of the worktree we're updating.
+	pub fn dir(&self) -> &Path
+	{
+		display_dir(&self.repo)
+	}
+
+	/// Writes the new index and files. Returns the number of files written and removed.
+	pub fn apply(&self) -> miette::Result<(usize, usize)>
+	{
+		assert!(self.lost.is_empty(), "applying a worktree update that would lose changes");
+
+		let repo = &self.repo;
+		let workdir = repo.workdir().expect("worktree updates are only planned for repos with worktrees");
+
+		let old_index = repo
+			.open_index()
+			.tap_err(|e| warn!("ignoring error reading existing index of {}: {}", workdir.display(), e))
+			.ok();
+
+		let mut new_index = repo
+			.index_from_tree(&self.new_tree)
+			.into_diagnostic()
+			.with_context(|| format!("while creating index for tree {}", self.new_tree))?;
+
+		let mut to_checkout: gix::index::State = (*new_index).clone();
+		to_checkout.remove_entries(|_idx, path, _entry| !self.write.contains(path));
+
+		// gix refuses to check out an empty index.
+		if !to_checkout.entries().is_empty() {
+			let mut options = repo
+				.checkout_options(gix::worktree::stack::state::attributes::Source::WorktreeThenIdMapping)
+				.into_diagnostic()?;
+			options.overwrite_existing = true;
+
+			let objects = repo
+				.objects
+				.clone()
+				.into_arc()
+				.into_diagnostic()?;
+
+			let outcome = gix::worktree::state::checkout(
+				&mut to_checkout,
+				workdir,
+				objects,
+				&gix::progress::Discard,
+				&gix::progress::Discard,
+				&AtomicBool::new(false),
+				options,
+			)
+				.into_diagnostic()
+				.with_context(|| format!("while checking out files in {}", workdir.display()))?;
+
+			for collision in &outcome.collisions {
+				warn!("could not check out {}: {:?}", collision.path, collision.error_kind);
+			}
+			for record in &outcome.errors {
+				warn!("could not check out {}: {}", record.path, record.error);
+			}
+		}
+
+		// Keep stat information for everything that is unchanged, so git doesn't have to
+		// rehash the whole worktree, and use the fresh stat information for what we wrote.
+		for (entry, path) in new_index.entries_mut_with_paths() {
+			if let Some(written) = to_checkout.entry_by_path_and_stage(path, Stage::Unconflicted) {
+				entry.stat = written.stat;
+			} else if let Some(old) = old_index.as_ref().and_then(|index| index.entry_by_path_and_stage(path, Stage::Unconflicted)) {
+				if old.id == entry.id && old.mode == entry.mode {
+					entry.stat = old.stat;
+				}
+			}
+		}
+
+		for path in &self.remove {
+			let full_path = workdir.join(gix::path::from_bstr(path.as_bstr()));
+			match std::fs::remove_file(&full_path) {
+				Ok(()) => (),
+				Err(e) if e.kind() == std::io::ErrorKind::NotFound => (),
+				Err(e) => warn!("could not remove {}: {}", full_path.display(), e),
+			}
+
+			// Clean up directories this leaves empty, like git does.
+			// remove_dir() refuses to remove non-empty directories, so this is safe.
+			let mut parent = full_path.parent();
+			while let Some(dir) = parent.filter(|dir| *dir != workdir) {
+				if std::fs::remove_dir(dir).is_err() {
+					break;
+				}
+				parent = dir.parent();
+			}
+		}
+
+		new_index
+			.write(Default::default())
+			.into_diagnostic()
+			.with_context(|| format!("while writing index {}", repo.index_path().display()))?;
+
+		Ok((self.write.len(), self.remove.len()))
+	}
+}
+
+/// The worktree directory of `repo`, or its git dir if it's bare.
+fn display_dir(repo: &Repository) -> &Path
+{
+	repo.workdir().unwrap_or_else(|| repo.git_dir())
+}
@@ -0,0 +1,114 @@
+//! Asking before risky changes, either for every change with `--interactive`, or for the
+//! changes `point.confirm` asks for, e.g. `refs/heads/main`, `rewind:refs/heads/*`, or
+//! `checked-out:*`, and asking which ref an ambiguous name was meant to be.
+
+use std::io::{BufRead, IsTerminal, Write};
+
+use bstr::BStr;
+use gix::Repository;
+
+#[allow(unused)]
+use log::{trace, debug, warn, info, error};
+
+use crate::policy::{self, Change, Condition, RefPolicy};
+
+/// Which changes to a matching ref need confirmation.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum When
+{
+	/// No condition given: creating or moving it at all.
+	Always,
+
+	/// `create`: creating it.
+	Create,
+
+	/// `move`: moving it once it exists.
+	Move,
+
+	/// `rewind`: moving it such that it no longer contains commits it did before.
+	Rewind,
+
+	/// `checked-out`: moving it while it's checked out in a worktree.
+	CheckedOut,
+}
+
+impl Condition for When
+{
+	const DEFAULT: Self = Self::Always;
+
+	const NAMES: &'static [(&'static str, Self)] = &[
+		("always", Self::Always),
+		("create", Self::Create),
+		("move", Self::Move),
+		("rewind", Self::Rewind),
+		("checked-out", Self::CheckedOut),
+	];
+}
+
+/// One `point.confirm` entry.
+pub type Confirmation = RefPolicy<When>;
+
+/// Every valid `point.confirm` entry in `repo`'s config.
+pub fn from_config(repo: &Repository) -> Vec<Confirmation>
+{
+	policy::from_config(repo, "point.confirm")
+}
+
+/// The first entry matching `name` that asks for confirmation of `change`, if any.
+pub fn required<'c>(confirmations: &'c [Confirmation], name: &BStr, change: Change) -> Option<&'c Confirmation>
+{
+	policy::first_applying(confirmations, name, |when| match (when, change) {
+		(When::Always, _) => true,
+		(When::Create, Change::Create) => true,
+		(When::Move, Change::Move { .. }) => true,
+		(When::Rewind, Change::Move { rewinds, .. }) => rewinds,
+		(When::CheckedOut, Change::Move { checked_out, .. }) => checked_out,
+		(When::Create, Change::Move { .. }) => false,
+		(When::Move | When::Rewind | When::CheckedOut, Change::Create) => false,
+	})
+}
+
+/// Whether there is somebody to ask, i.e. stdin is a terminal.
+pub fn can_ask() -> bool
+{
+	std::io::stdin().is_terminal()
+}
+
+/// Asks `question` on stderr and reads a yes or no answer from stdin, defaulting to no.
+pub fn ask(question: &str) -> std::io::Result<bool>
+{
+	let mut stderr = std::io::stderr().lock();
+	write!(stderr, "{} [y/N] ", question)?;
+	stderr.flush()?;
+
+	let mut answer = String::new();
+	std::io::stdin().lock().read_line(&mut answer)?;
+
+	let answer = answer.trim().to_ascii_lowercase();
+	Ok(answer == "y" || answer == "yes")
+}
+
+/// Asks `question` on stderr after listing `options`, and reads the number of one from stdin.
+///
+/// Returns `None` if nothing, or anything other than one of the numbers, was entered.
+pub fn choose(question: &str, options: &[String]) -> std::io::Result<Option<usize>>
+{
+	let mut stderr = std::io::stderr().lock();
+	for (idx, option) in options.iter().enumerate() {
+		writeln!(stderr, "  {}) {}", idx + 1, option)?;
+	}
+	write!(stderr, "{} [1-{}, or nothing to cancel] ", question, options.len())?;
+	stderr.flush()?;
+
+	let mut answer = String::new();
+	std::io::stdin().lock().read_line(&mut answer)?;
+
+	let choice = answer
+		.trim()
+		.parse::<usize>()
+		.ok()
+		.filter(|choice| (1..=options.len()).contains(choice))
+		.map(|choice| choice - 1);
+
+	Ok(choice)
+}
@@ -19,6 +19,7 @@ use gix::revision::plumbing::spec::parse::delegate::{
 };
 
 use crate::{RepositoryExt, MaybeAmbigRef};
+use crate::suggest;
 
 #[derive(Debug)]
 /// Gix revision parsing delegate which stubs everything except what we need to
@@ -91,6 +92,12 @@ impl<'repo> Revision for StubDisambDelegate<'repo>
 	{
         debug!("Delegate::find_ref({:?})", name);
 
+		if let Ok(None) = self.repo.try_find_reference(name) {
+			assert!(self.error.is_none());
+			self.error = Some(suggest::missing_ref(self.repo, name, false));
+			return None;
+		}
+
 		let maybe_ambiguous_refs = match self.repo.find_ambiguous_references(name) {
 			Ok(refs) => refs,
 			Err(e) => {
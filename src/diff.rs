@@ -0,0 +1,265 @@
+//! Diffstats and range-diffs between where a ref was and where it's going, computed with gix.
+
+use bstr::{BString, ByteSlice};
+use gix::diff::blob::intern::InternedInput;
+use gix::diff::blob::Algorithm;
+use gix::object::tree::diff::ChangeDetached as Change;
+use gix::{ObjectId, Repository};
+use miette::{Context, IntoDiagnostic};
+
+#[allow(unused)]
+use log::{trace, debug, warn, info, error};
+
+use crate::history::{self, CommitLine};
+
+/// How many bytes git looks at to decide if a file is binary.
+const BINARY_CHECK_LEN: usize = 8000;
+
+/// The changes to one file, like one line of `git diff --stat`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FileStat
+{
+	/// The file's path, or `old => new` if it was renamed.
+	pub path: BString,
+	pub insertions: u32,
+	pub deletions: u32,
+	pub binary: bool,
+}
+
+/// The contents of one changed file before and after.
+struct FileChange
+{
+	path: BString,
+	old: Vec<u8>,
+	new: Vec<u8>,
+}
+
+fn blob_data(repo: &Repository, id: &gix::oid) -> miette::Result<Vec<u8>>
+{
+	let object = repo
+		.find_object(id)
+		.into_diagnostic()
+		.with_context(|| format!("while finding blob {}", id))?;
+
+	Ok(object.detach().data)
+}
+
+fn is_binary(data: &[u8]) -> bool
+{
+	data[..data.len().min(BINARY_CHECK_LEN)].contains(&0)
+}
+
+fn tree_of(repo: &Repository, commit: ObjectId) -> miette::Result<gix::Tree<'_>>
+{
+	repo
+		.find_commit(commit)
+		.into_diagnostic()?
+		.tree()
+		.into_diagnostic()
+		.with_context(|| format!("while finding tree of commit {}", commit))
+}
+
+/// Every file that differs between `old_tree` and `new_tree`, where `None` is the empty tree.
+fn file_changes(repo: &Repository, old_tree: Option<&gix::Tree>, new_tree: &gix::Tree) -> miette::Result<Vec<FileChange>>
+{
+	let changes = repo
+		.diff_tree_to_tree(old_tree, Some(new_tree), None)
+		.into_diagnostic()
+		.with_context(|| format!("while diffing trees of {}", new_tree.id))?;
+
+	let mut files = Vec::new();
+	for change in changes {
+		// Trees are covered by the files in them, and submodules have no contents here.
+		let mode = change.entry_mode();
+		if mode.is_tree() || mode.is_commit() {
+			continue;
+		}
+
+		let file = match &change {
+			Change::Addition { location, id, .. } => FileChange {
+				path: location.clone(),
+				old: Vec::new(),
+				new: blob_data(repo, id)?,
+			},
+			Change::Deletion { location, id, .. } => FileChange {
+				path: location.clone(),
+				old: blob_data(repo, id)?,
+				new: Vec::new(),
+			},
+			Change::Modification { location, previous_id, id, .. } => FileChange {
+				path: location.clone(),
+				old: blob_data(repo, previous_id)?,
+				new: blob_data(repo, id)?,
+			},
+			Change::Rewrite { source_location, source_id, location, id, .. } => FileChange {
+				path: [source_location.as_bytes(), b" => ", location.as_bytes()].concat().into(),
+				old: blob_data(repo, source_id)?,
+				new: blob_data(repo, id)?,
+			},
+		};
+
+		files.push(file);
+	}
+
+	Ok(files)
+}
+
+/// The lines removed from `old` and added in `new`.
+fn changed_lines(old: &[u8], new: &[u8]) -> (Vec<BString>, Vec<BString>)
+{
+	let input = InternedInput::new(old, new);
+	let mut removed = Vec::new();
+	let mut added = Vec::new();
+
+	gix::diff::blob::diff(Algorithm::Histogram, &input, |before: std::ops::Range<u32>, after: std::ops::Range<u32>| {
+		for token in &input.before[before.start as usize..before.end as usize] {
+			removed.push(BString::from(input.interner[*token]));
+		}
+		for token in &input.after[after.start as usize..after.end as usize] {
+			added.push(BString::from(input.interner[*token]));
+		}
+	});
+
+	(removed, added)
+}
+
+/// The equivalent of `git diff --stat old new`.
+pub fn diffstat(repo: &Repository, old: ObjectId, new: ObjectId) -> miette::Result<Vec<FileStat>>
+{
+	let old_tree = tree_of(repo, old)?;
+	let new_tree = tree_of(repo, new)?;
+
+	let mut stats: Vec<FileStat> = file_changes(repo, Some(&old_tree), &new_tree)?
+		.into_iter()
+		.map(|file| {
+			if is_binary(&file.old) || is_binary(&file.new) {
+				return FileStat { path: file.path, insertions: 0, deletions: 0, binary: true };
+			}
+
+			let (removed, added) = changed_lines(&file.old, &file.new);
+			FileStat {
+				path: file.path,
+				insertions: added.len() as u32,
+				deletions: removed.len() as u32,
+				binary: false,
+			}
+		})
+		.collect();
+
+	// gix gives us tree order, git shows them sorted by path.
+	stats.sort_by(|a, b| a.path.cmp(&b.path));
+
+	Ok(stats)
+}
+
+/// Each changed file's path, with the lines removed from and added to it.
+type Patch = Vec<(BString, Vec<BString>, Vec<BString>)>;
+
+/// What a commit changes, ignoring line numbers and context, like `git patch-id`.
+/// Two commits with the same patch are the same change, even if they were rebased.
+fn patch_of(repo: &Repository, commit: ObjectId) -> miette::Result<Patch>
+{
+	let new_tree = tree_of(repo, commit)?;
+	let parent_tree = match repo.find_commit(commit).into_diagnostic()?.parent_ids().next() {
+		Some(parent) => Some(tree_of(repo, parent.detach())?),
+		None => None,
+	};
+
+	let patch = file_changes(repo, parent_tree.as_ref(), &new_tree)?
+		.into_iter()
+		.map(|file| {
+			let (removed, added) = changed_lines(&file.old, &file.new);
+			(file.path, removed, added)
+		})
+		.collect();
+
+	Ok(patch)
+}
+
+/// How a commit in the old series corresponds to one in the new series.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum RangeDiffEntry
+{
+	/// The same change in both, at these (1-based) positions.
+	Same { old: (usize, CommitLine), new: (usize, CommitLine) },
+
+	/// A commit with the same summary in both, but a different change.
+	Changed { old: (usize, CommitLine), new: (usize, CommitLine) },
+
+	/// Only in the old series.
+	Dropped((usize, CommitLine)),
+
+	/// Only in the new series.
+	Added((usize, CommitLine)),
+}
+
+/// The equivalent of `git range-diff old...new`: pairs up the commits each side has
+/// since their merge base.
+pub fn range_diff(repo: &Repository, old: ObjectId, new: ObjectId) -> miette::Result<Vec<RangeDiffEntry>>
+{
+	// Oldest first, like `git log --reverse`.
+	let series = |tip, other| -> miette::Result<Vec<(usize, CommitLine)>> {
+		let mut commits = history::only_in(repo, tip, other, usize::MAX)?;
+		commits.reverse();
+		Ok(commits.into_iter().enumerate().map(|(idx, commit)| (idx + 1, commit)).collect())
+	};
+	let old_series = series(old, new)?;
+	let new_series = series(new, old)?;
+
+	let old_patches = old_series
+		.iter()
+		.map(|(_pos, commit)| patch_of(repo, commit.id))
+		.collect::<miette::Result<Vec<_>>>()?;
+
+	// For each new commit, the index in old_series of the commit it corresponds to and whether it's the same change.
+	let mut matched_old = vec![false; old_series.len()];
+	let mut matches: Vec<Option<(usize, bool)>> = Vec::with_capacity(new_series.len());
+	for (_pos, new_commit) in &new_series {
+		let new_patch = patch_of(repo, new_commit.id)?;
+
+		let same = (0..old_series.len()).find(|&idx| !matched_old[idx] && old_patches[idx] == new_patch);
+		let changed = || {
+			(0..old_series.len()).find(|&idx| !matched_old[idx] && old_series[idx].1.summary == new_commit.summary)
+		};
+
+		let found = match same {
+			Some(idx) => Some((idx, true)),
+			None => changed().map(|idx| (idx, false)),
+		};
+		if let Some((idx, _same)) = found {
+			matched_old[idx] = true;
+		}
+		matches.push(found);
+	}
+
+	// Show new commits in order, with dropped old commits just before whatever came after them.
+	let mut entries = Vec::new();
+	let mut next_old = 0;
+	let mut push_dropped_until = |entries: &mut Vec<RangeDiffEntry>, until: usize| {
+		while next_old < until {
+			if !matched_old[next_old] {
+				entries.push(RangeDiffEntry::Dropped(old_series[next_old].clone()));
+			}
+			next_old += 1;
+		}
+	};
+
+	for (new_entry, found) in new_series.iter().zip(matches) {
+		match found {
+			Some((idx, same)) => {
+				push_dropped_until(&mut entries, idx);
+				let old = old_series[idx].clone();
+				let new = new_entry.clone();
+				entries.push(if same {
+					RangeDiffEntry::Same { old, new }
+				} else {
+					RangeDiffEntry::Changed { old, new }
+				});
+			},
+			None => entries.push(RangeDiffEntry::Added(new_entry.clone())),
+		}
+	}
+	push_dropped_until(&mut entries, old_series.len());
+
+	Ok(entries)
+}
@@ -0,0 +1,205 @@
+//! A compact ASCII commit graph of the history between a ref's old and new commits,
+//! like a small `git log --graph`.
+
+use std::collections::{HashMap, HashSet};
+
+use gix::{ObjectId, Repository};
+use miette::{Context, IntoDiagnostic};
+
+#[allow(unused)]
+use log::{trace, debug, warn, info, error};
+
+use crate::history::{self, CommitLine};
+
+/// One line of graph output.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Row
+{
+	/// A commit, with the lanes to its left, e.g. `| * `.
+	Commit { lanes: String, commit: CommitLine },
+
+	/// Lanes joining back together or forking off, e.g. `|/` or `|\\`.
+	Edges(String),
+}
+
+/// The commits each side has since their merge base, plus the merge bases themselves,
+/// newest first with every commit before its parents.
+///
+/// Returns whether either side had more than `max_per_side` commits which were left out.
+pub fn window(repo: &Repository, old: ObjectId, new: ObjectId, max_per_side: usize) -> miette::Result<(Vec<CommitLine>, bool)>
+{
+	// Get one more than we show, so we know if there are more.
+	let mut gained = history::only_in(repo, new, old, max_per_side + 1)?;
+	let mut lost = history::only_in(repo, old, new, max_per_side + 1)?;
+	let truncated = gained.len() > max_per_side || lost.len() > max_per_side;
+	gained.truncate(max_per_side);
+	lost.truncate(max_per_side);
+
+	let mut commits: Vec<CommitLine> = gained.into_iter().chain(lost).collect();
+	for base in history::merge_bases(repo, old, new)? {
+		commits.push(CommitLine { id: base, summary: history::summary_of(repo, base)? });
+	}
+
+	Ok((topo_sort(repo, commits)?, truncated))
+}
+
+/// The parents of `id`.
+fn parents_of(repo: &Repository, id: ObjectId) -> miette::Result<Vec<ObjectId>>
+{
+	let commit = repo
+		.find_commit(id)
+		.into_diagnostic()
+		.with_context(|| format!("while finding commit {}", id))?;
+
+	Ok(commit.parent_ids().map(|parent| parent.detach()).collect())
+}
+
+/// Orders `commits` so each comes before its parents, following one line of history as far
+/// as possible before switching to another, like `git log --topo-order`.
+fn topo_sort(repo: &Repository, commits: Vec<CommitLine>) -> miette::Result<Vec<CommitLine>>
+{
+	let ids: HashSet<ObjectId> = commits.iter().map(|commit| commit.id).collect();
+
+	let mut parents: HashMap<ObjectId, Vec<ObjectId>> = HashMap::new();
+	let mut child_counts: HashMap<ObjectId, usize> = HashMap::new();
+	for commit in &commits {
+		let in_window: Vec<ObjectId> = parents_of(repo, commit.id)?
+			.into_iter()
+			.filter(|parent| ids.contains(parent))
+			.collect();
+		for parent in &in_window {
+			*child_counts.entry(*parent).or_default() += 1;
+		}
+		parents.insert(commit.id, in_window);
+	}
+
+	let mut by_id: HashMap<ObjectId, CommitLine> = commits
+		.iter()
+		.map(|commit| (commit.id, commit.clone()))
+		.collect();
+
+	// Tips, in the order we were given them. Reversed, since we pop from the end.
+	let mut ready: Vec<ObjectId> = commits
+		.iter()
+		.rev()
+		.map(|commit| commit.id)
+		.filter(|id| !child_counts.contains_key(id))
+		.collect();
+
+	let mut sorted = Vec::with_capacity(commits.len());
+	while let Some(id) = ready.pop() {
+		sorted.push(by_id.remove(&id).expect("every commit is only ready once"));
+
+		// Push in reverse, so the first parent is continued next.
+		for parent in parents[&id].iter().rev() {
+			let count = child_counts.get_mut(parent).expect("parents in the window have child counts");
+			*count -= 1;
+			if *count == 0 {
+				ready.push(*parent);
+			}
+		}
+	}
+
+	Ok(sorted)
+}
+
+/// Lays out `commits` (from [window]) into lanes.
+pub fn render(repo: &Repository, commits: &[CommitLine]) -> miette::Result<Vec<Row>>
+{
+	let ids: HashSet<ObjectId> = commits.iter().map(|commit| commit.id).collect();
+
+	// Each lane holds the commit we expect to draw in it next.
+	let mut lanes: Vec<Option<ObjectId>> = Vec::new();
+	let mut rows = Vec::new();
+
+	for commit in commits {
+		let mut columns: Vec<usize> = lanes
+			.iter()
+			.enumerate()
+			.filter(|(_col, lane)| **lane == Some(commit.id))
+			.map(|(col, _lane)| col)
+			.collect();
+
+		// Other lanes waiting on this commit join into the leftmost one.
+		let joining = columns.split_off(columns.len().min(1));
+
+		let column = match columns.first() {
+			Some(&column) => column,
+			None => {
+				let free = lanes.iter().position(Option::is_none);
+				let column = free.unwrap_or(lanes.len());
+				if free.is_none() {
+					lanes.push(None);
+				}
+				column
+			},
+		};
+
+		if !joining.is_empty() {
+			rows.push(Row::Edges(edges(&lanes, &joining, b'/')));
+
+			for col in joining {
+				lanes[col] = None;
+			}
+			while lanes.last() == Some(&None) {
+				lanes.pop();
+			}
+		}
+
+		let row_lanes: String = lanes
+			.iter()
+			.enumerate()
+			.map(|(col, lane)| match lane {
+				_ if col == column => "* ",
+				Some(_) => "| ",
+				None => "  ",
+			})
+			.collect();
+		rows.push(Row::Commit { lanes: row_lanes, commit: commit.clone() });
+
+		// This lane continues with the first parent, and any other parents get lanes of their own.
+		let parents: Vec<ObjectId> = parents_of(repo, commit.id)?
+			.into_iter()
+			.filter(|parent| ids.contains(parent))
+			.collect();
+		lanes[column] = parents.first().copied();
+		let mut forking = Vec::new();
+		for parent in parents.iter().skip(1) {
+			if lanes.contains(&Some(*parent)) {
+				continue;
+			}
+			let free = lanes.iter().position(Option::is_none).unwrap_or(lanes.len());
+			if free == lanes.len() {
+				lanes.push(None);
+			}
+			lanes[free] = Some(*parent);
+			forking.push(free);
+		}
+
+		if !forking.is_empty() {
+			rows.push(Row::Edges(edges(&lanes, &forking, b'\\')));
+		}
+
+		while lanes.last() == Some(&None) {
+			lanes.pop();
+		}
+	}
+
+	Ok(rows)
+}
+
+/// A row of `lanes`, where those in `bending` bend towards the lane to their left with `edge`,
+/// like `|/` or `|\\`.
+fn edges(lanes: &[Option<ObjectId>], bending: &[usize], edge: u8) -> String
+{
+	let mut row = vec![b' '; lanes.len() * 2];
+	for (col, lane) in lanes.iter().enumerate() {
+		if bending.contains(&col) && col > 0 {
+			row[col * 2 - 1] = edge;
+		} else if lane.is_some() {
+			row[col * 2] = b'|';
+		}
+	}
+
+	String::from_utf8_lossy(&row).trim_end().to_owned()
+}
@@ -0,0 +1,152 @@
+//! The commits that separate two points in history, for showing what moving a ref
+//! would gain or lose.
+
+use std::collections::HashSet;
+
+use bstr::{BString, ByteSlice};
+use gix::revision::walk::Sorting;
+use gix::traverse::commit::simple::CommitTimeOrder;
+use gix::{ObjectId, Repository};
+use miette::{Context, IntoDiagnostic};
+
+#[allow(unused)]
+use log::{trace, debug, warn, info, error};
+
+/// A commit as `git log --oneline` would show it.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CommitLine
+{
+	pub id: ObjectId,
+	pub summary: BString,
+}
+
+pub fn summary_of(repo: &Repository, id: ObjectId) -> miette::Result<BString>
+{
+	let commit = repo
+		.find_commit(id)
+		.into_diagnostic()
+		.with_context(|| format!("while finding commit {}", id))?;
+
+	let summary = commit
+		.message_raw_sloppy()
+		.lines()
+		.next()
+		.unwrap_or(b"<empty msg>");
+
+	Ok(summary.into())
+}
+
+/// All merge bases of `one` and `two`, which is empty if their histories are unrelated.
+pub fn merge_bases(repo: &Repository, one: ObjectId, two: ObjectId) -> miette::Result<Vec<ObjectId>>
+{
+	let cache = repo
+		.commit_graph_if_enabled()
+		.into_diagnostic()?;
+	let mut graph = repo.revision_graph(cache.as_ref());
+
+	let bases = repo
+		.merge_bases_many_with_graph(one, &[two], &mut graph)
+		.into_diagnostic()
+		.with_context(|| format!("while finding merge bases of {} and {}", one, two))?;
+
+	Ok(bases.into_iter().map(|id| id.detach()).collect())
+}
+
+/// Every commit reachable from `tips`, including themselves.
+fn ancestors(repo: &Repository, tips: &[ObjectId]) -> miette::Result<HashSet<ObjectId>>
+{
+	if tips.is_empty() {
+		return Ok(HashSet::new());
+	}
+
+	let walk = repo
+		.rev_walk(tips.iter().copied())
+		.all()
+		.into_diagnostic()
+		.context("while walking history")?;
+
+	walk
+		.map(|info| Ok(info.into_diagnostic().context("while walking history")?.id))
+		.collect()
+}
+
+/// The IDs of up to `max` commits reachable from `tip` but not from `other`, newest first,
+/// like `git rev-list other..tip`.
+pub fn only_in_ids(repo: &Repository, tip: ObjectId, other: ObjectId, max: usize) -> miette::Result<Vec<ObjectId>>
+{
+	// Whatever `tip` and `other` have in common is exactly what's reachable from their merge
+	// bases, so everything else `tip` can reach is only in `tip`. Unlike stopping a walk by
+	// commit date, this stays right when commit dates are out of order.
+	let bases = merge_bases(repo, tip, other)?;
+	if bases.contains(&tip) {
+		return Ok(Vec::new());
+	}
+	let common = ancestors(repo, &bases)?;
+
+	// `common` includes all of its own ancestors, so nothing only in `tip` is behind it.
+	let walk = repo
+		.rev_walk([tip])
+		.sorting(Sorting::ByCommitTime(CommitTimeOrder::NewestFirst))
+		.selected(move |id| !common.contains(id))
+		.into_diagnostic()
+		.with_context(|| format!("while walking history from {}", tip))?;
+
+	walk
+		.take(max)
+		.map(|info| {
+			Ok(info
+				.into_diagnostic()
+				.with_context(|| format!("while walking history from {}", tip))?
+				.id)
+		})
+		.collect()
+}
+
+/// Up to `max` commits reachable from `tip` but not from `other`, newest first,
+/// like `git log other..tip`.
+pub fn only_in(repo: &Repository, tip: ObjectId, other: ObjectId, max: usize) -> miette::Result<Vec<CommitLine>>
+{
+	lines_of(repo, &only_in_ids(repo, tip, other, max)?)
+}
+
+/// Looks up the summaries of `ids`.
+pub fn lines_of(repo: &Repository, ids: &[ObjectId]) -> miette::Result<Vec<CommitLine>>
+{
+	ids
+		.iter()
+		.map(|&id| Ok(CommitLine { id, summary: summary_of(repo, id)? }))
+		.collect()
+}
+
+/// The root commit `tip`'s history starts from, following first parents.
+pub fn first_parent_root(repo: &Repository, tip: ObjectId) -> miette::Result<CommitLine>
+{
+	let walk = repo
+		.rev_walk([tip])
+		.first_parent_only()
+		.all()
+		.into_diagnostic()
+		.with_context(|| format!("while walking history from {}", tip))?;
+
+	let mut root = None;
+	for info in walk {
+		root = Some(info
+			.into_diagnostic()
+			.with_context(|| format!("while walking history from {}", tip))?
+			.id);
+	}
+	let root = root.unwrap_or(tip);
+
+	Ok(CommitLine { id: root, summary: summary_of(repo, root)? })
+}
+
+/// Describes moving a ref which gains `ahead` commits and loses `behind`.
+pub fn describe_move(ahead: usize, behind: usize) -> String
+{
+	match (ahead, behind) {
+		(0, 0) => String::from("no change"),
+		(ahead, 0) => format!("fast-forward, {} ahead", ahead),
+		(0, behind) => format!("rewind, {} behind", behind),
+		(ahead, behind) => format!("divergent, {} ahead, {} behind", ahead, behind),
+	}
+}
@@ -1,5 +1,6 @@
 #![doc = include_str!("../README.md")]
 
+use std::collections::{HashMap, HashSet};
 use std::env;
 use std::io::{IsTerminal, Write};
 use std::iter;
@@ -16,18 +17,35 @@ use gix::refs::transaction::LogChange;
 use gix::refs::transaction::PreviousValue;
 use gix::refs::transaction::RefEdit;
 use gix::refs::transaction::RefLog;
-use gix::refs::{FullName, Target};
+use gix::refs::{FullName, FullNameRef, Target};
 use gix::refs::Category as RefCategory;
+use gix::state::InProgress;
 use gix::Id as GixId;
+use gix::prelude::{ObjectIdExt, ReferenceExt};
 use gix::Reference;
 use gix::Repository;
+use gix::ObjectId;
 
 #[allow(unused)]
 use log::{trace, debug, warn, info, error};
 
 use tap::TapFallible;
 
+mod checkout;
+mod confirm;
 mod delegate;
+mod diff;
+mod graph;
+mod history;
+mod operation;
+mod policy;
+mod protect;
+mod refname;
+mod reflog;
+mod remote;
+mod suggest;
+mod track;
+mod worktree;
 
 /// Like OwoColorize, but gate styling on an arbitary boolean condition.
 pub trait MaybeStyle: OwoColorize
@@ -69,20 +87,25 @@ impl<T: OwoColorize> MaybeStyle for T
 pub enum MaybeAmbigRef<'repo>
 {
 	Ambiguous { requested: BString, possible: Vec<BString> },
-    // This field isn't actually used right now (because all code paths
-    // already have the ref some other way), but like, it *could* be, y'know?
-    #[allow(dead_code)]
 	NotAmbiguous(Reference<'repo>),
 }
 
 pub trait RepositoryExt
 {
-	fn find_ambiguous_references(&self, refname: &BStr) -> miette::Result<MaybeAmbigRef>;
+	fn find_ambiguous_references(&self, refname: &BStr) -> miette::Result<MaybeAmbigRef<'_>>;
+
+	/// Finds existing refs that would prevent creating `full_name`, because one of them
+	/// is a directory of the other (e.g. `refs/heads/feature` and `refs/heads/feature/x`).
+	fn find_conflicting_references(&self, full_name: &BStr) -> miette::Result<Vec<Reference<'_>>>;
+
+	/// Finds all existing refs which shorten to `short`, e.g. both `refs/heads/v1.0`
+	/// and `refs/tags/v1.0` for `v1.0`.
+	fn find_references_by_short_name(&self, short: &BStr) -> miette::Result<Vec<Reference<'_>>>;
 }
 
 impl RepositoryExt for Repository
 {
-	fn find_ambiguous_references(&self, refname: &BStr) -> miette::Result<MaybeAmbigRef>
+	fn find_ambiguous_references(&self, refname: &BStr) -> miette::Result<MaybeAmbigRef<'_>>
 	{
 		let reference = self
 			.find_reference(refname)
@@ -124,6 +147,92 @@ impl RepositoryExt for Repository
 
 		Ok(MaybeAmbigRef::Ambiguous{ requested: refname.to_owned(), possible: ambiguous_ref_names, })
 	}
+
+	fn find_conflicting_references(&self, full_name: &BStr) -> miette::Result<Vec<Reference<'_>>>
+	{
+		let is_dir_of = |dir: &[u8], name: &[u8]| {
+			name.len() > dir.len() && name.starts_with(dir) && name[dir.len()] == b'/'
+		};
+
+		// Like find_ambiguous_references(), iterating through everything covers both
+		// loose and packed refs.
+		let refs_iter = self
+			.references()
+			.into_diagnostic()?;
+
+		let conflicting = refs_iter
+			.all()
+			.into_diagnostic()?
+			.filter_map(|r| match r {
+				Ok(r) => {
+					let existing = r.name().as_bstr();
+					if is_dir_of(existing, full_name) || is_dir_of(full_name, existing) {
+						// Iterated refs borrow from the iterator, not the repo.
+						Some(r.detach().attach(self))
+					} else {
+						None
+					}
+				},
+				Err(e) => {
+					warn!("ignoring error checking for conflicting reference: {}", e);
+					None
+				},
+			})
+			.collect();
+
+		Ok(conflicting)
+	}
+
+	fn find_references_by_short_name(&self, short: &BStr) -> miette::Result<Vec<Reference<'_>>>
+	{
+		let refs_iter = self
+			.references()
+			.into_diagnostic()?;
+
+		let same_short = refs_iter
+			.all()
+			.into_diagnostic()?
+			.filter_map(|r| match r {
+				Ok(r) if r.name().shorten() == short => Some(r.detach().attach(self)),
+				Ok(_) => None,
+				Err(e) => {
+					warn!("ignoring error checking for ambiguous reference: {}", e);
+					None
+				},
+			})
+			.collect();
+
+		Ok(same_short)
+	}
+}
+
+/// The full hex ID `reference` peels to, or a placeholder if it can't be peeled.
+fn peeled_hex_or_placeholder(reference: &Reference) -> String
+{
+	reference
+		.clone()
+		.into_fully_peeled_id()
+		.map(|peeled| peeled.to_hex().to_string())
+		.unwrap_or_else(|e| {
+			warn!("error resolving existing ref {}: {}", reference.name().as_bstr(), e);
+			String::from("<could not resolve>")
+		})
+}
+
+/// What kind of ref `name` is, for messages.
+pub fn category_name(name: &FullNameRef) -> &'static str
+{
+	match name.category() {
+		Some(RefCategory::Tag) => "tag",
+		Some(RefCategory::LocalBranch) => "branch",
+		Some(RefCategory::RemoteBranch) => "remote branch",
+		Some(RefCategory::Note) => "note",
+		Some(RefCategory::Bisect) => "bisect ref",
+		Some(RefCategory::Rewritten) => "rebase ref",
+		Some(RefCategory::PseudoRef | RefCategory::MainPseudoRef | RefCategory::LinkedPseudoRef { .. }) => "pseudoref",
+		Some(RefCategory::WorktreePrivate | RefCategory::MainRef | RefCategory::LinkedRef { .. }) => "worktree ref",
+		None => "ref",
+	}
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd, Hash)]
@@ -157,6 +266,81 @@ impl NewRefKind
 			Raw => BStr::new(b""),
 		}
 	}
+
+	/// The name of this kind as it's given to `--new`.
+	fn name(self) -> &'static str
+	{
+		use NewRefKind::*;
+		match self {
+			Tag => "tag",
+			Branch => "branch",
+			RemoteBranch => "remote-branch",
+			Raw => "raw",
+		}
+	}
+
+	/// Prefixes which would be redundant, and almost certainly a mistake, at the start of
+	/// the name of a ref of this kind (e.g. `tags/` for `--new=tag tags/v1.0`).
+	fn redundant_prefixes(self) -> &'static [&'static str]
+	{
+		use NewRefKind::*;
+		match self {
+			Tag => &["refs/", "tags/"],
+			Branch => &["refs/", "heads/"],
+			RemoteBranch => &["refs/", "remotes/"],
+			Raw => &[],
+		}
+	}
+
+	/// Infers the kind of a new ref from an already qualified name, like `refs/tags/v1.0`,
+	/// `heads/topic`, or `origin/main` (if `origin` is a remote).
+	///
+	/// Returns the kind, and the name with the kind's prefix removed.
+	fn infer(repo: &Repository, qualified: &BStr) -> miette::Result<(Self, BString)>
+	{
+		use NewRefKind::*;
+
+		let explicit: [(&[u8], Self); 6] = [
+			(RefCategory::Tag.prefix(), Tag),
+			(RefCategory::LocalBranch.prefix(), Branch),
+			(RefCategory::RemoteBranch.prefix(), RemoteBranch),
+			(b"tags/", Tag),
+			(b"heads/", Branch),
+			(b"remotes/", RemoteBranch),
+		];
+		for (prefix, kind) in explicit {
+			if let Some(rest) = qualified.strip_prefix(prefix) {
+				return Ok((kind, BString::from(rest)));
+			}
+		}
+
+		if qualified.starts_with(b"refs/") {
+			return Ok((Raw, qualified.to_owned()));
+		}
+
+		// Another worktree's per-worktree ref, e.g. `worktrees/<id>/refs/worktree/x`.
+		if let (worktree::Owner::Main | worktree::Owner::Linked(_), inner) = worktree::split(qualified) {
+			if inner.starts_with(b"refs/") {
+				return Ok((Raw, qualified.to_owned()));
+			}
+		}
+
+		if let Some((remote, _branch)) = qualified.split_once_str("/") {
+			let is_remote = repo
+				.remote_names()
+				.iter()
+				.any(|name| name.as_ref() == remote.as_bstr());
+			if is_remote {
+				return Ok((RemoteBranch, qualified.to_owned()));
+			}
+		}
+
+		Err(miette::miette!(
+			help = format!("qualify it (e.g. 'heads/{0}' or 'tags/{0}'), or pass --new=<KIND> instead", qualified),
+			"cannot infer what kind of ref '{}' should be",
+			qualified,
+		))
+	}
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -164,7 +348,8 @@ impl NewRefKind
 #[command(version, author, about)]
 struct GitPointCmd
 {
-	/// ref to update
+	/// ref to update. Another worktree's per-worktree refs (like `HEAD` or `refs/bisect/bad`)
+	/// can be named as `main-worktree/<ref>` or `worktrees/<id>/<ref>`, like in git.
 	#[arg(required_unless_present = "mangen")]
 	pub from: Option<String>,
 
@@ -172,15 +357,124 @@ struct GitPointCmd
 	#[arg(required_unless_present = "mangen")]
 	pub to: Option<String>,
 
-	/// create a new ref of <KIND> instead of updating an existing one
-	#[arg(short, long, action = ArgAction::Set, value_name = "KIND")]
-	pub new: Option<NewRefKind>,
+	/// create a new ref of <KIND> instead of updating an existing one. Without a <KIND>, it's
+	/// inferred from an already qualified <FROM> (e.g. `git point --new refs/tags/v1.0 @`)
+	#[arg(short, long, num_args = 0..=1, require_equals = true, value_name = "KIND")]
+	pub new: Option<Option<NewRefKind>>,
+
+	/// With `--new=remote-branch`, also create refs/remotes/<remote>/HEAD pointing to the new ref.
+	/// Refuses with status 2 if refs/remotes/<remote>/HEAD already exists.
+	#[arg(long, action = ArgAction::SetTrue)]
+	pub remote_head: bool,
+
+	/// With `--new=branch`, set up the new branch to track the branch <TO> names.
+	/// Defaults to following branch.autoSetupMerge.
+	#[arg(long, action = ArgAction::SetTrue, conflicts_with = "no_track")]
+	pub track: bool,
+
+	/// With `--new=branch`, never set up the new branch to track anything.
+	#[arg(long, action = ArgAction::SetTrue)]
+	pub no_track: bool,
+
+	/// Allow creating a new ref whose short name is the same as an existing ref's.
+	#[arg(long, action = ArgAction::SetTrue)]
+	pub allow_ambiguous: bool,
+
+	/// Allow creating or moving refs protected by `point.protect` in git config.
+	/// Each value is a ref pattern like `refs/tags/*`, optionally preceded by a rule:
+	/// `ff-only:<pattern>` only allows fast-forwards, and `create-only:<pattern>` only allows
+	/// creating refs, never moving them. Refusals exit with status 4.
+	#[arg(long, action = ArgAction::SetTrue)]
+	pub force_protected: bool,
+
+	/// Allow moving existing tags, which are otherwise refused (with status 4), since moving
+	/// a tag others may have already fetched is almost always a mistake.
+	#[arg(long, action = ArgAction::SetTrue)]
+	pub retag: bool,
+
+	/// Allow pointing a ref at a commit which shares no history with the one it points to now,
+	/// which is otherwise refused with status 5.
+	#[arg(long, action = ArgAction::SetTrue)]
+	pub allow_unrelated: bool,
+
+	/// Also list the commits <FROM> gains and loses by moving, like `git log --oneline`.
+	#[arg(long, action = ArgAction::SetTrue)]
+	pub log: bool,
+
+	/// Also show a diffstat between the commit <FROM> pointed to and <TO>.
+	#[arg(long, action = ArgAction::SetTrue)]
+	pub stat: bool,
+
+	/// Also show a range-diff between the commits <FROM> and <TO> each have since their merge base,
+	/// e.g. to see what changed when pointing a topic branch at a rebased version of it.
+	#[arg(long, action = ArgAction::SetTrue)]
+	pub range_diff: bool,
+
+	/// Also draw a commit graph around the merge base of the commit <FROM> pointed to and <TO>,
+	/// marking where <FROM> was and is now, and other refs nearby.
+	#[arg(long, action = ArgAction::SetTrue)]
+	pub graph: bool,
+
+	/// Show the planned change and ask before making it. Also done for changes `point.confirm`
+	/// in git config asks for: each value is a ref pattern, optionally preceded by when to ask:
+	/// `create:<pattern>`, `move:<pattern>`, `rewind:<pattern>` for moves that lose commits, or
+	/// `checked-out:<pattern>` for moves of refs checked out in a worktree.
+	/// Exits with status 6 if declined, or if stdin is not a terminal.
+	#[arg(short, long, action = ArgAction::SetTrue, conflicts_with = "yes")]
+	pub interactive: bool,
+
+	/// Never ask anything, e.g. from scripts: make changes `point.confirm` asks to confirm, and
+	/// refuse ambiguous ref names rather than asking which ref was meant.
+	#[arg(short, long, action = ArgAction::SetTrue)]
+	pub yes: bool,
+
+	/// Why the ref is being changed, which is added to its reflog entry.
+	/// Defaults to the GIT_POINT_REASON environment variable, if it's set.
+	/// The whole reflog message can be customized with a template in `point.reflogMessage`,
+	/// using the placeholders {ref}, {old}, {new}, {change} (the default message), {reason},
+	/// and {caller} (the command line of the process that ran git-point, where available).
+	#[arg(short, long, value_name = "REASON")]
+	pub message: Option<String>,
+
+	/// Don't update anything, only check that <FROM> points to the commit <TO> resolves to,
+	/// resolving both exactly like an update would. Exits with status 8 if it doesn't.
+	#[arg(long, action = ArgAction::SetTrue, conflicts_with_all = ["new", "update_worktree", "interactive", "expect"])]
+	pub check: bool,
+
+	/// Only update <FROM> if it currently points to <REV>, even if it's moved since git-point
+	/// started, for safely updating refs other tools or people may be updating too.
+	/// Exits with status 8 if it points anywhere else.
+	#[arg(long, value_name = "REV", conflicts_with = "new")]
+	pub expect: Option<String>,
+
+	/// Exit with status 7 if <FROM> already points to <TO>, so there was nothing to do.
+	/// Otherwise, that's treated as success, like any other update.
+	#[arg(long, action = ArgAction::SetTrue)]
+	pub exit_code: bool,
 
 	/// Allow mutating checked out refs.
 	/// This will *not* change any of the actual files in the worktree.
 	#[arg(long, short = 'W', action = ArgAction::SetTrue)]
 	pub allow_worktree: bool,
 
+	/// Run as if git-point was started in <PATH> instead of the current directory.
+	/// Like git, multiple -C options are each interpreted relative to the previous one.
+	#[arg(short = 'C', value_name = "PATH", action = ArgAction::Append)]
+	pub directory: Vec<PathBuf>,
+
+	/// Path to the repository's git directory, like GIT_DIR.
+	#[arg(long, value_name = "PATH")]
+	pub git_dir: Option<PathBuf>,
+
+	/// Path to the repository's worktree, like GIT_WORK_TREE.
+	#[arg(long, value_name = "PATH")]
+	pub work_tree: Option<PathBuf>,
+
+	/// Move checked out refs, and update the index and files of the worktrees they're checked out in.
+	/// Modes work like the same options to `git reset`.
+	#[arg(long, value_name = "MODE", conflicts_with = "allow_worktree")]
+	pub update_worktree: Option<checkout::UpdateMode>,
+
 	/// When to use terminal colors
 	#[arg(long, default_value = "auto")]
 	pub color: clap::ColorChoice,
@@ -200,6 +494,7 @@ enum Victim<'repo>
 #[derive(Debug, Clone, PartialEq, Hash)]
 struct NewVictim
 {
+	kind: NewRefKind,
 	revspec: BString,
 	/// The fully qualified name of the ref, e.g. refs/heads/main.
 	name: BString,
@@ -236,6 +531,10 @@ struct KnownVictim<'repo>
 	/// points to, before the mutation.
 	resolved_id: GixId<'repo>,
 
+	/// What the ref itself stores before the mutation, which for an annotated tag is
+	/// the tag object rather than `resolved_id`.
+	stored: Target,
+
 	/// The first line of the commit message.
 	summary: BString,
 }
@@ -269,21 +568,54 @@ impl<'repo> KnownVictim<'repo>
 			name: reference.name().to_owned(),
 			short: reference.name().shorten().to_owned(),
 			resolved_id: peeled,
+			stored: reference.target().into_owned(),
 			summary: BString::from(commit_summary.to_vec()),
 		})
 	}
 
+	/// What the ref must still store when we update it.
 	pub fn transaction_target(&self) -> Target
 	{
-		Target::Object(self.resolved_id.into())
+		self.stored.clone()
+	}
+
+	/// The tag object the ref points to, if it's an annotated tag.
+	pub fn tag_object(&self) -> Option<ObjectId>
+	{
+		match &self.stored {
+			Target::Object(id) if *id != self.resolved_id.detach() => Some(*id),
+			_ => None,
+		}
 	}
 }
 
 impl NewVictim
 {
-    pub fn new(kind: NewRefKind, revspec: BString) -> Self
+    /// Fails with a diagnostic pointing at the problem if the resulting ref name
+    /// would not be valid.
+    pub fn new(kind: NewRefKind, revspec: BString) -> miette::Result<Self>
     {
+        let redundant = kind
+            .redundant_prefixes()
+            .iter()
+            .find(|&prefix| revspec.starts_with(prefix.as_bytes()));
+        if let Some(redundant) = redundant {
+            return Err(miette::miette!(
+                help = format!(
+                    "pass --new without a <KIND> to infer it from <FROM>, or drop the '{}' from <FROM>",
+                    redundant,
+                ),
+                "refusing to create {} '{}' as {}{}, which is probably not what you meant",
+                kind.name(),
+                revspec,
+                kind.to_prefix(),
+                revspec,
+            ));
+        }
+
         let prefix = kind.to_prefix();
+        refname::validate_new(prefix, revspec.as_bstr())?;
+
         let refname: BString = prefix
             .iter()
             .chain(revspec.as_bytes())
@@ -291,12 +623,20 @@ impl NewVictim
             .collect();
         debug!("going to create ref {}", &refname);
 
-        Self {
+        if kind == NewRefKind::Raw && !worktree::split(refname.as_bstr()).1.starts_with(b"refs/") {
+            warn!(
+                "creating raw ref {} outside of refs/; git may not treat it as a ref",
+                &refname,
+            );
+        }
+
+        Ok(Self {
+            kind,
             revspec,
             short: refname.strip_prefix(prefix.as_bytes()).unwrap_or(&refname).into(),
 			// lol, has to be in this order to avoid a clone().
 			name: refname,
-        }
+        })
     }
 }
 
@@ -313,13 +653,17 @@ struct TargetRev<'repo>
 	/// The first line of the commit message.
 	summary: BString,
 
-	symref: Option<BString>,
+	/// The ref `revspec` names, if it is *just* a ref name (e.g. `origin/main`, but not `origin/main~2`).
+	symref: Option<FullName>,
 }
 
 impl<'repo> TargetRev<'repo>
 {
 	/// Constructs [TargetRev] from a revspec.
-	pub fn from(repo: &'repo Repository, revspec: BString, should_color: bool) -> miette::Result<Self>
+	///
+	/// If `can_pick` is true, an ambiguous ref name in `revspec` is resolved by asking which
+	/// ref was meant.
+	pub fn from(repo: &'repo Repository, revspec: BString, can_pick: bool, should_color: bool) -> miette::Result<Self>
 	{
         // Bit of a hack here.
         // Gitoxide doesn't really have a way to use only part of its rev parsing logic.
@@ -349,19 +693,45 @@ impl<'repo> TargetRev<'repo>
 		};
         let found_refs = revparsing_delegate.found_refs;
 
-        if let Some(MaybeAmbigRef::Ambiguous { requested, possible }) = found_refs {
+        if let Some(MaybeAmbigRef::Ambiguous { requested, possible }) = &found_refs {
+			let candidates = Candidate::resolve_all(repo, possible);
+			// The ambiguous name replaced with a candidate's qualified one.
+			let revspec_with = |qualified: &BStr| revspec.replacen(requested.as_bytes(), qualified.as_bytes(), 1);
+
+			if can_pick {
+				if let Some(chosen) = pick_ambiguous(repo, requested.as_bstr(), &candidates, should_color)? {
+					// Other names in `revspec` may be ambiguous too, so go through it all again.
+					return Self::from(repo, revspec_with(chosen.as_bstr()).into(), can_pick, should_color);
+				}
+			}
+
             eprintln!(
                 "{} refname '{}' in '{}' is ambiguous and must be qualified; \
-                could be any of: {}",
+                could be any of:",
 				"error:".style_as_error_if(should_color),
                 requested.style_as_ref_if(should_color),
                 revspec,
-                bstr::join(", ", possible).as_bstr(),
             );
+			print_ambiguity_details(
+				repo,
+				&candidates,
+				|qualified| command_replacing(&revspec.to_str_lossy(), &revspec_with(qualified).to_str_lossy(), true),
+				should_color,
+			);
 
             std::process::exit(3);
         };
 
+        let symref = match &found_refs {
+            Some(MaybeAmbigRef::NotAmbiguous(found)) => repo
+                .try_find_reference(revspec.as_bstr())
+                .ok()
+                .flatten()
+                .filter(|direct| direct.name() == found.name())
+                .map(|direct| direct.name().to_owned()),
+            _ => None,
+        };
+
         let rev_id = repo.rev_parse_single(revspec.as_bstr())
 			.into_diagnostic()
 			.with_context(|| format!("while parsing revspec {}", revspec.as_bstr()))?;
@@ -371,7 +741,9 @@ impl<'repo> TargetRev<'repo>
 			.object()
 			.into_diagnostic()
 			.with_context(|| format!("while finding object {}", rev_hex()))?
-			.into_commit();
+			.peel_to_commit()
+			.into_diagnostic()
+			.with_context(|| format!("while peeling {} to a commit", rev_hex()))?;
 
 		let summary = commit
 			.message_raw()
@@ -383,9 +755,9 @@ impl<'repo> TargetRev<'repo>
 
 		Ok(Self {
 			revspec,
-			resolved_id: rev_id,
+			resolved_id: commit.id(),
 			summary: BString::from(summary.to_vec()),
-			symref: None,
+			symref,
 		})
 	}
 
@@ -395,15 +767,179 @@ impl<'repo> TargetRev<'repo>
 	}
 }
 
-/// Will std::process:exit() if check condition matches.
-fn check_worktrees(repo: &Repository, victim_ref: &Reference, should_color: bool)
+/// One of the refs an ambiguous name could refer to.
+struct Candidate
+{
+	/// The name that refers to it from any worktree, which can be used instead of the ambiguous one.
+	qualified: BString,
+	category: &'static str,
+	/// The commit it points to, if it could be resolved to one.
+	commit: Option<history::CommitLine>,
+}
+
+impl Candidate
+{
+	/// Looks up what each of the `possible` full names points to.
+	fn resolve_all(repo: &Repository, possible: &[BString]) -> Vec<Self>
+	{
+		possible
+			.iter()
+			.map(|name| {
+				let commit = repo
+					.find_reference(name.as_bstr())
+					.into_diagnostic()
+					.and_then(|mut reference| reference.peel_to_id_in_place().into_diagnostic())
+					.and_then(|id| Ok(history::CommitLine { id: id.detach(), summary: history::summary_of(repo, id.detach())? }))
+					.tap_err(|e| warn!("error resolving ambiguous ref {}: {}", name, e))
+					.ok();
+
+				let category = FullName::try_from(name.clone())
+					.map(|full| category_name(full.as_ref()))
+					.unwrap_or("ref");
+
+				Self {
+					// Per-worktree refs are qualified, so they can be copied to refer to the right one.
+					qualified: worktree::qualify(repo, name.as_bstr()),
+					category,
+					commit,
+				}
+			})
+			.collect()
+	}
+
+	/// A line describing this candidate, with its name and category padded to the given widths.
+	fn describe(&self, repo: &Repository, name_width: usize, category_width: usize, should_color: bool) -> String
+	{
+		let commit = match &self.commit {
+			Some(commit) => format!(
+				"{} {}",
+				commit.id.attach(repo).shorten_or_id().style_as_commit_if(should_color),
+				commit.summary.as_bstr(),
+			),
+			None => String::from("<could not resolve>"),
+		};
+
+		// Pad before styling, so escape codes don't count towards the width.
+		let padding = " ".repeat(name_width.saturating_sub(self.qualified.len()));
+		format!(
+			"{}{} {:<category_width$} {}",
+			self.qualified.as_bstr().style_as_ref_if(should_color),
+			padding,
+			format!("({})", self.category),
+			commit,
+		)
+	}
+}
+
+/// Describes each of `candidates`, aligned.
+fn describe_candidates(repo: &Repository, candidates: &[Candidate], should_color: bool) -> Vec<String>
+{
+	let name_width = candidates.iter().map(|candidate| candidate.qualified.len()).max().unwrap_or(0);
+	// With parentheses.
+	let category_width = candidates.iter().map(|candidate| candidate.category.len() + 2).max().unwrap_or(0);
+	candidates
+		.iter()
+		.map(|candidate| candidate.describe(repo, name_width, category_width, should_color))
+		.collect()
+}
+
+/// Quotes `arg` for a POSIX shell, if it needs it.
+fn shell_quote(arg: &str) -> String
+{
+	let is_safe = |c: char| c.is_ascii_alphanumeric() || "_-./:@^~=+,%".contains(c);
+	if !arg.is_empty() && arg.chars().all(is_safe) {
+		return arg.to_owned();
+	}
+
+	format!("'{}'", arg.replace('\'', r"'\''"))
+}
+
+/// This invocation as a `git point` command line, with the argument `original` replaced by
+/// `replacement`, or `None` if no argument was `original`.
+///
+/// If `last` is true the last matching argument is replaced, otherwise the first.
+fn command_replacing(original: &str, replacement: &str, last: bool) -> Option<String>
+{
+	let mut args: Vec<String> = env::args().skip(1).collect();
+	let position = if last {
+		args.iter().rposition(|arg| arg == original)
+	} else {
+		args.iter().position(|arg| arg == original)
+	}?;
+	args[position] = replacement.to_owned();
+
+	let words: Vec<String> = args.iter().map(|arg| shell_quote(arg)).collect();
+	Some(format!("git point {}", words.join(" ")))
+}
+
+/// Prints the details of an ambiguity error: what each candidate points to, whether that's all
+/// the same commit, and the command for each, where `command_for` gives the command that
+/// uses a candidate's qualified name.
+fn print_ambiguity_details<F>(repo: &Repository, candidates: &[Candidate], command_for: F, should_color: bool)
+where
+	F: Fn(&BStr) -> Option<String>,
+{
+	for line in describe_candidates(repo, candidates, should_color) {
+		eprintln!("  {}", line);
+	}
+
+	let mut commits = candidates.iter().map(|candidate| candidate.commit.as_ref().map(|commit| commit.id));
+	let first = commits.next().flatten();
+	match first {
+		Some(first) if commits.all(|id| id == Some(first)) => eprintln!(
+			"They all point to {}.",
+			first.attach(repo).shorten_or_id().style_as_commit_if(should_color),
+		),
+		_ => eprintln!("They point to different commits."),
+	}
+
+	let commands: Option<Vec<String>> = candidates
+		.iter()
+		.map(|candidate| command_for(candidate.qualified.as_bstr()))
+		.collect();
+	if let Some(commands) = commands {
+		eprintln!("To use one of them, run:");
+		for command in commands {
+			eprintln!("  {}", command);
+		}
+	}
+}
+
+/// Asks which of the `candidates` the ambiguous `requested` name was meant to be,
+/// showing what each of them points to.
+///
+/// Returns the qualified name of the chosen one, if any.
+fn pick_ambiguous(repo: &Repository, requested: &BStr, candidates: &[Candidate], should_color: bool) -> miette::Result<Option<BString>>
+{
+	let options = describe_candidates(repo, candidates, should_color);
+
+	eprintln!("'{}' is ambiguous, and could be any of:", requested.style_as_ref_if(should_color));
+	let choice = confirm::choose("Which one did you mean?", &options)
+		.into_diagnostic()
+		.context("while asking which ref was meant")?;
+
+	Ok(choice.map(|idx| candidates[idx].qualified.clone()))
+}
+
+/// Every worktree of `repo`'s repository, including the main one if it has one.
+fn all_worktree_repos(repo: &Repository) -> Vec<Repository>
 {
 	let worktrees = repo
 		.worktrees()
 		.tap_err(|e| warn!("ignoring error finding active worktrees: {}", e))
 		.unwrap_or_else(|_e| Vec::new());
 
-	let worktree_repos = worktrees
+	// If we're in a linked worktree, `repo` is that worktree, and the main one
+	// is wherever the common dir is.
+	let main_repo = if repo.git_dir() == repo.common_dir() {
+		Some(repo.clone())
+	} else {
+		gix::open(repo.common_dir())
+			.tap_err(|e| warn!("ignoring error opening main repository {}: {}", repo.common_dir().display(), e))
+			.ok()
+	};
+
+	worktrees
 		.into_iter()
 		.filter_map(|worktree| {
 			let id = worktree.id().to_owned();
@@ -412,31 +948,814 @@ fn check_worktrees(repo: &Repository, victim_ref: &Reference, should_color: bool
 				.tap_err(|e| warn!("ignoring error accessing worktree {}: {}", id, e))
 				.ok()
 		})
-		.chain(iter::once(repo.clone()));
+		.chain(main_repo)
+		// A bare repository's HEAD names a ref, but nothing is checked out there,
+		// so (like git) we don't consider it in use.
+		.filter(|tree_repo| {
+			if tree_repo.workdir().is_none() {
+				debug!("not checking bare repository {}", tree_repo.git_dir().display());
+			}
+			tree_repo.workdir().is_some()
+		})
+		.collect()
+}
 
-	for tree_repo in worktree_repos {
-		let dir = tree_repo.workdir().expect("unreachable");
-		debug!("checking if worktree {} has {} checked out", dir.display(), victim_ref.name().as_bstr());
+/// The worktrees which have `victim_name` checked out, or which are using it for an
+/// in-progress operation like a rebase or bisect, along with that operation.
+///
+/// Per-worktree refs, like `HEAD`, `refs/bisect/*`, or `worktrees/<id>/HEAD`, are only
+/// considered in the worktree they belong to.
+fn worktrees_with_checked_out(repo: &Repository, victim_name: &FullNameRef) -> Vec<(Repository, Option<InProgress>)>
+{
+	let (owner, name) = worktree::split(victim_name.as_bstr());
+	let is_private = worktree::is_private(name);
 
-		let tree_head = tree_repo
-			.head_ref()
-			.tap_err(|e| warn!("ignoring error discovering worktree {} HEAD: {}", dir.display(), e));
-		let Ok(tree_head) = tree_head else {
-			continue;
-		};
+	all_worktree_repos(repo)
+		.into_iter()
+		.filter_map(|tree_repo| {
+			let dir = tree_repo.workdir().expect("bare repos are already filtered out");
+			debug!("checking if worktree {} has {} checked out", dir.display(), name);
 
-		if tree_head.as_ref().map(|r| &r.inner) == Some(&victim_ref.inner) {
-			eprintln!(
+			let is_owner = worktree::is_owner(repo, &tree_repo, owner);
+			if is_private && !is_owner {
+				return None;
+			}
+
+			// A worktree's HEAD is whatever it has checked out, even if it's detached.
+			let is_head = if is_private {
+				name == "HEAD"
+			} else {
+				let tree_head = tree_repo
+					.head_ref()
+					.tap_err(|e| warn!("ignoring error discovering worktree {} HEAD: {}", dir.display(), e));
+				matches!(tree_head, Ok(Some(head)) if head.name().as_bstr() == name)
+			};
+
+			if is_head {
+				// HEAD stays attached during merges and cherry-picks, but they're still worth mentioning.
+				let operation = tree_repo.state();
+				return Some((tree_repo, operation));
+			}
+
+			let operation = operation::using_ref(&tree_repo, name, is_owner)?;
+
+			Some((tree_repo, Some(operation)))
+		})
+		.collect()
+}
+
+/// The repository of the worktree whose refs `name` refers to, which is `repo` unless it
+/// names another worktree's per-worktree ref.
+fn owner_repo(repo: &Repository, name: &BStr) -> miette::Result<Repository>
+{
+	let (owner, inner) = worktree::split(name);
+	if owner == worktree::Owner::Current || !worktree::is_private(inner) {
+		return Ok(repo.clone());
+	}
+
+	all_worktree_repos(repo)
+		.into_iter()
+		.find(|tree_repo| worktree::is_owner(repo, tree_repo, owner))
+		.ok_or_else(|| miette::miette!(
+			help = "see `git worktree list`",
+			"{} refers to a worktree that does not exist",
+			name,
+		))
+}
+
+/// Will std::process:exit() if check condition matches.
+///
+/// If `updating` is true, worktrees which simply have the victim checked out are fine, since we
+/// will update them, but ones in the middle of an operation still aren't.
+fn check_worktrees(repo: &Repository, victim_ref: &Reference, updating: bool, should_color: bool)
+{
+	// Shortening `worktrees/<id>/HEAD` would lose which worktree it is.
+	let victim_name = victim_ref.name();
+	let display_name = if worktree::is_private(worktree::split(victim_name.as_bstr()).1) {
+		victim_name.as_bstr()
+	} else {
+		victim_name.shorten()
+	};
+
+	for (tree_repo, operation) in worktrees_with_checked_out(repo, victim_name) {
+		let dir = tree_repo.workdir().expect("bare repos are already filtered out");
+
+		match operation {
+			None if updating => continue,
+			None => eprintln!(
 				"{} refusing to update ref {} checked out at {}; \
-				pass --allow-worktree to override",
+				pass --allow-worktree or --update-worktree to override",
+				"error:".style_as_error_if(should_color),
+				display_name.style_as_ref_if(should_color),
+				dir.display(),
+			),
+			Some(operation) => eprintln!(
+				"{} refusing to update ref {} in use by {} in progress at {}; \
+				finish or abort it first, or pass --allow-worktree to override",
 				"error:".style_as_error_if(should_color),
-				victim_ref.name().shorten().style_as_ref_if(should_color),
+				display_name.style_as_ref_if(should_color),
+				operation::describe(&operation),
 				dir.display(),
+			),
+		}
+
+		std::process::exit(1);
+	}
+}
+
+/// Plans updating every worktree which has `victim` checked out from its old commit to `target`.
+///
+/// Will std::process::exit() if that would lose local changes in any of them.
+fn plan_worktree_updates(
+	repo: &Repository,
+	victim: &KnownVictim,
+	target: &TargetRev,
+	mode: checkout::UpdateMode,
+	should_color: bool,
+) -> miette::Result<Vec<checkout::WorktreeUpdate>>
+{
+	let mut updates = Vec::new();
+
+	for (tree_repo, operation) in worktrees_with_checked_out(repo, victim.name.as_ref()) {
+		// check_worktrees() already refused anything with an operation in progress.
+		debug_assert!(operation.is_none());
+
+		let update = checkout::WorktreeUpdate::plan(
+			tree_repo,
+			victim.resolved_id.detach(),
+			target.resolved_id.detach(),
+			mode,
+		)?;
+
+		if !update.lost.is_empty() {
+			eprintln!(
+				"{} refusing to update worktree {}, as local changes to these files would be lost: {}; \
+				commit or stash them, or pass --update-worktree=hard to discard them",
+				"error:".style_as_error_if(should_color),
+				update.dir().display(),
+				bstr::join(", ", &update.lost).as_bstr(),
 			);
 
 			std::process::exit(1);
 		}
+
+		updates.push(update);
+	}
+
+	Ok(updates)
+}
+
+/// For --check: exits the process successfully if `victim` points to `target`, and with
+/// status 8 otherwise, after showing where each points like a diff.
+fn check_points_to(repo: &Repository, victim: &KnownVictim, target: &TargetRev, should_color: bool) -> miette::Result<()>
+{
+	if victim.resolved_id == target.resolved_id {
+		eprintln!(
+			"{} points to {} ({})",
+			victim.name.as_bstr().style_as_ref_if(should_color),
+			target.resolved_id.shorten_or_id().style_as_commit_if(should_color),
+			target.summary.as_bstr(),
+		);
+
+		std::process::exit(0);
+	}
+
+	eprintln!(
+		"{} {} does not point to {}",
+		"error:".style_as_error_if(should_color),
+		victim.name.as_bstr().style_as_ref_if(should_color),
+		target.revspec.as_bstr().style_as_ref_if(should_color),
+	);
+	eprintln!("  --- expected ({})", target.revspec.as_bstr());
+	eprintln!("  +++ actual ({})", victim.name.as_bstr());
+	eprintln!(
+		"  {} {} {}",
+		"-".style_if(should_color, Style::new().red()),
+		target.resolved_id.shorten_or_id().style_as_commit_if(should_color),
+		target.summary.as_bstr(),
+	);
+	eprintln!(
+		"  {} {} {}",
+		"+".style_if(should_color, Style::new().green()),
+		victim.resolved_id.shorten_or_id().style_as_commit_if(should_color),
+		victim.summary.as_bstr(),
+	);
+
+	// Say how far off it is, like the summary of an update would.
+	let old = victim.resolved_id.detach();
+	let new = target.resolved_id.detach();
+	if history::merge_bases(repo, old, new)?.is_empty() {
+		eprintln!("  ({} shares no history with {})", victim.short.as_bstr(), target.revspec.as_bstr());
+	} else {
+		let ahead = history::only_in_ids(repo, old, new, usize::MAX)?.len();
+		let behind = history::only_in_ids(repo, new, old, usize::MAX)?.len();
+		eprintln!(
+			"  ({} is {} ahead and {} behind {})",
+			victim.short.as_bstr(),
+			ahead,
+			behind,
+			target.revspec.as_bstr(),
+		);
+	}
+
+	std::process::exit(8);
+}
+
+/// Will std::process::exit() if the ref `name` no longer points to `expected_id`.
+///
+/// Otherwise returns what it stores, which for an annotated tag is the tag object that peels to
+/// `expected_id`.
+fn check_not_moved(repo: &Repository, name: &FullNameRef, expected_id: ObjectId, should_color: bool) -> miette::Result<Target>
+{
+	let (stored, actual_id) = repo
+		.find_reference(name)
+		.into_diagnostic()
+		.and_then(|mut reference| {
+			let stored = reference.target().into_owned();
+			let actual_id = reference.peel_to_id_in_place().into_diagnostic()?.detach();
+			Ok((stored, actual_id))
+		})
+		.with_context(|| format!("while checking where {} points", name.as_bstr()))?;
+
+	if actual_id == expected_id {
+		return Ok(stored);
+	}
+
+	let describe = |id: ObjectId| -> miette::Result<String> {
+		Ok(format!(
+			"{} ({})",
+			id.attach(repo).shorten_or_id().style_as_commit_if(should_color),
+			history::summary_of(repo, id)?,
+		))
+	};
+
+	eprintln!(
+		"{} ref {} has moved: expected it to point to {}, but it points to {}",
+		"error:".style_as_error_if(should_color),
+		name.as_bstr().style_as_ref_if(should_color),
+		describe(expected_id)?,
+		describe(actual_id)?,
+	);
+
+	std::process::exit(8);
+}
+
+/// Updates the files of each worktree as planned by [plan_worktree_updates].
+fn apply_worktree_updates(updates: &[checkout::WorktreeUpdate]) -> miette::Result<()>
+{
+	for update in updates {
+		let (written, removed) = update
+			.apply()
+			.with_context(|| format!("while updating worktree {}", update.dir().display()))?;
+
+		eprintln!(
+			"Updated worktree {} ({}): {} file(s) written, {} removed",
+			update.dir().display(),
+			update.mode().name(),
+			written,
+			removed,
+		);
+	}
+
+	Ok(())
+}
+
+/// Will std::process::exit() if `victim` and `target` have no common ancestor, which
+/// usually means `target` is from a different project entirely.
+fn check_related(repo: &Repository, victim: &KnownVictim, target: &TargetRev, should_color: bool) -> miette::Result<()>
+{
+	let old = victim.resolved_id.detach();
+	let new = target.resolved_id.detach();
+
+	if !history::merge_bases(repo, old, new)?.is_empty() {
+		return Ok(());
+	}
+
+	eprintln!(
+		"{} refusing to point {} at {} ({}), which shares no history with {} ({}); \
+		pass --allow-unrelated to override",
+		"error:".style_as_error_if(should_color),
+		victim.short.as_bstr().style_as_ref_if(should_color),
+		target.resolved_id.shorten_or_id().style_as_commit_if(should_color),
+		target.summary.as_bstr(),
+		victim.resolved_id.shorten_or_id().style_as_commit_if(should_color),
+		victim.summary.as_bstr(),
+	);
+
+	for (which, tip) in [(victim.short.as_bstr(), old), (target.revspec.as_bstr(), new)] {
+		let root = history::first_parent_root(repo, tip)?;
+		eprintln!(
+			"  {} starts from root commit {} ({})",
+			which,
+			root.id.attach(repo).shorten_or_id().style_as_commit_if(should_color),
+			root.summary.as_bstr(),
+		);
+	}
+
+	std::process::exit(5);
+}
+
+/// Prints the previews asked for with --log, --stat, --range-diff, and --graph, of moving
+/// `victim` to `target`, which gains the commits in `gained` and loses those in `lost`.
+fn print_previews(
+	repo: &Repository,
+	args: &GitPointCmd,
+	victim: &KnownVictim,
+	target: &TargetRev,
+	gained: &[ObjectId],
+	lost: &[ObjectId],
+	should_color: bool,
+) -> miette::Result<()>
+{
+	if args.log {
+		print_commit_lines(repo, "+", &history::lines_of(repo, gained)?, usize::MAX, should_color);
+		print_commit_lines(repo, "-", &history::lines_of(repo, lost)?, usize::MAX, should_color);
+	}
+
+	if args.stat {
+		let stats = diff::diffstat(repo, victim.resolved_id.detach(), target.resolved_id.detach())?;
+		print_diffstat(&stats, should_color);
+	}
+
+	if args.range_diff {
+		let entries = diff::range_diff(repo, victim.resolved_id.detach(), target.resolved_id.detach())?;
+		print_range_diff(repo, &entries, should_color);
+	}
+
+	if args.graph {
+		print_graph(repo, victim, target, should_color)?;
+	}
+
+	Ok(())
+}
+
+/// With --interactive, or if `point.confirm` asks for it, shows the planned change and asks
+/// whether to go ahead, returning whether the previews were shown as part of it.
+///
+/// Will std::process::exit() if the change is declined, or if there's nobody to ask.
+fn confirm_change(
+	repo: &Repository,
+	args: &GitPointCmd,
+	victim: &Victim,
+	target: &TargetRev,
+	movement: Option<&(Vec<ObjectId>, Vec<ObjectId>)>,
+	worktree_updates: &[checkout::WorktreeUpdate],
+	should_color: bool,
+) -> miette::Result<bool>
+{
+	let checked_out: Vec<Repository> = match victim {
+		Victim::Known(known) => worktrees_with_checked_out(repo, known.name.as_ref())
+			.into_iter()
+			.map(|(tree_repo, _operation)| tree_repo)
+			.collect(),
+		Victim::New(_) => Vec::new(),
+	};
+
+	let confirmations = confirm::from_config(repo);
+	let required_by = confirm::required(&confirmations, victim.name_bstr(), planned_change(repo, victim, movement));
+	if !args.interactive && required_by.is_none() {
+		return Ok(false);
+	}
+
+	let (action, question_action, done) = match victim {
+		Victim::Known(_) => ("update", "Update", "updated"),
+		Victim::New(_) => ("create", "Create", "created"),
+	};
+
+	if !confirm::can_ask() {
+		let (why, help) = match required_by {
+			Some(confirmation) if !args.interactive => (
+				format!(", which point.confirm={} asks to confirm,", confirmation.display()),
+				"pass --yes to confirm",
+			),
+			_ => (String::new(), "run it in a terminal or without --interactive"),
+		};
+		eprintln!(
+			"{} refusing to {} ref {}{} since stdin is not a terminal to ask for confirmation; {}",
+			"error:".style_as_error_if(should_color),
+			action,
+			victim.name_bstr().style_as_ref_if(should_color),
+			why,
+			help,
+		);
+		std::process::exit(6);
+	}
+
+	match (victim, movement) {
+		(Victim::Known(known), Some((gained, lost))) => {
+			eprintln!(
+				"About to update {refname} from {previd} ({prevmsg}) to {newid} ({newmsg}) [{movement}]",
+				refname = known.name.as_bstr().style_as_ref_if(should_color),
+				previd = known.resolved_id.shorten_or_id().style_as_commit_if(should_color),
+				prevmsg = known.summary.as_bstr(),
+				newid = target.resolved_id.shorten_or_id().style_as_commit_if(should_color),
+				newmsg = target.summary.as_bstr(),
+				movement = history::describe_move(gained.len(), lost.len()),
+			);
+
+			for tree_repo in &checked_out {
+				let dir = tree_repo.workdir().expect("bare repos are already filtered out");
+				let update = worktree_updates.iter().find(|update| update.dir() == dir);
+				match update {
+					Some(update) => eprintln!(
+						"  checked out at {}, which will be updated with --update-worktree={}",
+						dir.display(),
+						update.mode().name(),
+					),
+					None => eprintln!("  checked out at {}, whose files will not be changed", dir.display()),
+				}
+			}
+
+			print_previews(repo, args, known, target, gained, lost, should_color)?;
+		},
+		_ => eprintln!(
+			"About to create {refname} at {target_id} ({msg})",
+			refname = victim.name_bstr().style_as_ref_if(should_color),
+			target_id = target.resolved_id.shorten_or_id().style_as_commit_if(should_color),
+			msg = target.summary,
+		),
+	}
+
+	let question = match required_by {
+		Some(confirmation) if !args.interactive => format!(
+			"point.confirm={} asks to confirm this. {} {}?",
+			confirmation.display(),
+			question_action,
+			victim.name_bstr(),
+		),
+		_ => format!("{} {}?", question_action, victim.name_bstr()),
+	};
+
+	let confirmed = confirm::ask(&question)
+		.into_diagnostic()
+		.context("while asking for confirmation")?;
+	if !confirmed {
+		eprintln!("Aborted; {} was not {}", victim.name_bstr().style_as_ref_if(should_color), done);
+		std::process::exit(6);
+	}
+
+	Ok(true)
+}
+
+/// Prints up to `max` of `commits` like `git log --oneline`, each prefixed with `sign`.
+fn print_commit_lines(repo: &Repository, sign: &str, commits: &[history::CommitLine], max: usize, should_color: bool)
+{
+	for commit in commits.iter().take(max) {
+		eprintln!(
+			"  {} {} {}",
+			sign,
+			commit.id.attach(repo).shorten_or_id().style_as_commit_if(should_color),
+			commit.summary.as_bstr(),
+		);
+	}
+	if commits.len() > max {
+		eprintln!("  {} ...", sign);
+	}
+}
+
+/// The widest a diffstat's +/- bar gets.
+const DIFFSTAT_BAR_WIDTH: usize = 40;
+
+/// Prints `stats` like `git diff --stat`.
+fn print_diffstat(stats: &[diff::FileStat], should_color: bool)
+{
+	let path_width = stats.iter().map(|stat| stat.path.len()).max().unwrap_or(0);
+	let max_changes = stats.iter().map(|stat| stat.insertions + stat.deletions).max().unwrap_or(0) as usize;
+	let count_width = max_changes.to_string().len();
+
+	// Scale the bars down if the biggest change wouldn't fit, but keep every change visible.
+	let scale = |n: u32| {
+		let n = n as usize;
+		if max_changes <= DIFFSTAT_BAR_WIDTH || n == 0 {
+			n
+		} else {
+			(n * DIFFSTAT_BAR_WIDTH / max_changes).max(1)
+		}
+	};
+
+	for stat in stats {
+		let path = stat.path.to_str_lossy();
+		if stat.binary {
+			eprintln!("  {:path_width$} | Bin", path);
+			continue;
+		}
+
+		let pluses = "+".repeat(scale(stat.insertions));
+		let minuses = "-".repeat(scale(stat.deletions));
+		eprintln!(
+			"  {:path_width$} | {:>count_width$} {}{}",
+			path,
+			stat.insertions + stat.deletions,
+			pluses.style_if(should_color, Style::new().green()),
+			minuses.style_if(should_color, Style::new().red()),
+		);
+	}
+
+	let insertions: u32 = stats.iter().map(|stat| stat.insertions).sum();
+	let deletions: u32 = stats.iter().map(|stat| stat.deletions).sum();
+	let plural = |n: usize, word: &str| format!("{} {}{}", n, word, if n == 1 { "" } else { "s" });
+	eprintln!(
+		"  {} changed, {}(+), {}(-)",
+		plural(stats.len(), "file"),
+		plural(insertions as usize, "insertion"),
+		plural(deletions as usize, "deletion"),
+	);
+}
+
+/// Prints `entries` like `git range-diff`.
+fn print_range_diff(repo: &Repository, entries: &[diff::RangeDiffEntry], should_color: bool)
+{
+	use diff::RangeDiffEntry::*;
+
+	let short = |commit: &history::CommitLine| commit.id.attach(repo).shorten_or_id().to_string();
+	let placeholder = |commit: &history::CommitLine| "-".repeat(short(commit).len());
+
+	for entry in entries {
+		let (old_pos, old_id, sign, new_pos, new_id, summary) = match entry {
+			Same { old, new } => (old.0.to_string(), short(&old.1), "=", new.0.to_string(), short(&new.1), &new.1.summary),
+			Changed { old, new } => (old.0.to_string(), short(&old.1), "!", new.0.to_string(), short(&new.1), &new.1.summary),
+			Dropped(old) => (old.0.to_string(), short(&old.1), "<", String::from("-"), placeholder(&old.1), &old.1.summary),
+			Added(new) => (String::from("-"), placeholder(&new.1), ">", new.0.to_string(), short(&new.1), &new.1.summary),
+		};
+
+		eprintln!(
+			"  {}: {} {} {}: {} {}",
+			old_pos,
+			old_id.style_as_commit_if(should_color),
+			sign,
+			new_pos,
+			new_id.style_as_commit_if(should_color),
+			summary.as_bstr(),
+		);
+	}
+}
+
+/// How many commits to show on each side of the merge base in --graph.
+const GRAPH_MAX_COMMITS: usize = 15;
+
+/// Draws the history between where `victim` was and `target`, with refs pointing into it.
+fn print_graph(repo: &Repository, victim: &KnownVictim, target: &TargetRev, should_color: bool) -> miette::Result<()>
+{
+	let old = victim.resolved_id.detach();
+	let new = target.resolved_id.detach();
+
+	let (commits, truncated) = graph::window(repo, old, new, GRAPH_MAX_COMMITS)?;
+
+	// Every other ref pointing at a commit in the window, by commit.
+	let window_ids: HashSet<ObjectId> = commits.iter().map(|commit| commit.id).collect();
+	let mut decorations: HashMap<ObjectId, Vec<BString>> = HashMap::new();
+	let refs_platform = repo
+		.references()
+		.into_diagnostic()?;
+	let refs = refs_platform
+		.all()
+		.into_diagnostic()?;
+	for reference in refs {
+		let mut reference = match reference {
+			Ok(reference) => reference,
+			Err(e) => {
+				warn!("ignoring error reading refs for graph: {}", e);
+				continue;
+			},
+		};
+		if reference.name() == victim.name.as_ref() {
+			continue;
+		}
+		let Ok(id) = reference.peel_to_id_in_place() else {
+			continue;
+		};
+		if window_ids.contains(&id.detach()) {
+			decorations
+				.entry(id.detach())
+				.or_default()
+				.push(reference.name().shorten().to_owned());
+		}
+	}
+
+	for row in graph::render(repo, &commits)? {
+		let (lanes, commit) = match row {
+			graph::Row::Edges(edges) => {
+				eprintln!("  {}", edges);
+				continue;
+			},
+			graph::Row::Commit { lanes, commit } => (lanes, commit),
+		};
+
+		let mut labels: Vec<String> = Vec::new();
+		if commit.id == old {
+			labels.push(format!("{} was here", victim.short));
+		}
+		if commit.id == new {
+			labels.push(format!("{} will be here", victim.short));
+		}
+		let others = decorations.get(&commit.id).map(|names| bstr::join(", ", names)).unwrap_or_default();
+		let others = if others.is_empty() { String::new() } else { format!(" ({})", others.as_bstr()) };
+		let labels = if labels.is_empty() { String::new() } else { format!(" <- {}", labels.join(", ")) };
+
+		eprintln!(
+			"  {}{} {}{}{}",
+			lanes,
+			commit.id.attach(repo).shorten_or_id().style_as_commit_if(should_color),
+			commit.summary.as_bstr(),
+			others.style_as_ref_if(should_color),
+			labels.style_as_ref_if(should_color),
+		);
+	}
+
+	if truncated {
+		eprintln!("  (more than {} commits on one side; older ones are not shown)", GRAPH_MAX_COMMITS);
+	}
+
+	Ok(())
+}
+
+/// How many commits to show on each side when refusing to move a tag.
+const RETAG_MAX_COMMITS: usize = 10;
+
+/// Will std::process::exit(), explaining what moving tag `victim` to `target` would change.
+fn refuse_retag(repo: &Repository, victim: &KnownVictim, target: &TargetRev, should_color: bool) -> miette::Result<()>
+{
+	let old = victim.resolved_id.detach();
+	let new = target.resolved_id.detach();
+
+	// Get one more than we show, so we know if there are more.
+	let gained = history::only_in(repo, new, old, RETAG_MAX_COMMITS + 1)?;
+	let lost = history::only_in(repo, old, new, RETAG_MAX_COMMITS + 1)?;
+
+	eprintln!(
+		"{} refusing to move tag {} from {} ({}) to {} ({}); pass --retag to override",
+		"error:".style_as_error_if(should_color),
+		victim.short.as_bstr().style_as_ref_if(should_color),
+		victim.resolved_id.shorten_or_id().style_as_commit_if(should_color),
+		victim.summary.as_bstr(),
+		target.resolved_id.shorten_or_id().style_as_commit_if(should_color),
+		target.summary.as_bstr(),
+	);
+
+	print_commit_lines(repo, "+", &gained, RETAG_MAX_COMMITS, should_color);
+	print_commit_lines(repo, "-", &lost, RETAG_MAX_COMMITS, should_color);
+
+	std::process::exit(4);
+}
+
+/// Describes the change to `victim` which gains and loses the commits in `movement`,
+/// for `point.protect` and `point.confirm`.
+fn planned_change(repo: &Repository, victim: &Victim, movement: Option<&(Vec<ObjectId>, Vec<ObjectId>)>) -> policy::Change
+{
+	match (victim, movement) {
+		(Victim::Known(known), Some((_gained, lost))) => policy::Change::Move {
+			rewinds: !lost.is_empty(),
+			checked_out: !worktrees_with_checked_out(repo, known.name.as_ref()).is_empty(),
+		},
+		_ => policy::Change::Create,
+	}
+}
+
+/// Will std::process::exit() if a `point.protect` entry forbids the change to `victim`
+/// which gains and loses the commits in `movement`.
+fn check_protected(
+	repo: &Repository,
+	victim: &Victim,
+	movement: Option<&(Vec<ObjectId>, Vec<ObjectId>)>,
+	should_color: bool,
+)
+{
+	let protections = protect::from_config(repo);
+	if protections.is_empty() {
+		return;
+	}
+
+	let Some(protection) = protect::violated(&protections, victim.name_bstr(), planned_change(repo, victim, movement)) else {
+		return;
+	};
+
+	let action = match victim {
+		Victim::Known(_) => "update",
+		Victim::New(_) => "create",
+	};
+	let reason = match protection.condition {
+		protect::Rule::Deny => "",
+		protect::Rule::CreateOnly => ", which only allows creating it",
+		protect::Rule::FastForwardOnly => ", which only allows fast-forwarding it",
+	};
+
+	eprintln!(
+		"{} refusing to {} ref {} protected by point.protect={}{}; \
+		pass --force-protected to override",
+		"error:".style_as_error_if(should_color),
+		action,
+		victim.name_bstr().style_as_ref_if(should_color),
+		protection.display(),
+		reason,
+	);
+
+	std::process::exit(4);
+}
+
+/// Will std::process::exit() if `new_victim` can't be created because of an existing ref
+/// of the same path prefix.
+fn check_ref_conflicts(repo: &Repository, new_victim: &NewVictim, should_color: bool)
+{
+	let conflicting = repo
+		.find_conflicting_references(worktree::split(new_victim.name.as_bstr()).1)
+		.tap_err(|e| warn!("ignoring error checking for refs conflicting with {}: {}", new_victim.name, e))
+		.unwrap_or_default();
+
+	if conflicting.is_empty() {
+		return;
 	}
+
+	let conflicting_descs: Vec<String> = conflicting
+		.iter()
+		.map(|existing_ref| {
+			format!(
+				"{} at {}",
+				existing_ref.name().as_bstr().style_as_ref_if(should_color),
+				peeled_hex_or_placeholder(existing_ref).style_as_commit_if(should_color),
+			)
+		})
+		.collect();
+
+	eprintln!(
+		"{} refusing to create ref {} which conflicts with existing ref(s): {}",
+		"error:".style_as_error_if(should_color),
+		new_victim.name.as_bstr().style_as_ref_if(should_color),
+		conflicting_descs.join(", "),
+	);
+
+	std::process::exit(2);
+}
+
+/// Will std::process::exit() if `new_victim`'s short name is already used by another ref,
+/// which would make it ambiguous once created.
+fn check_new_ambiguity(repo: &Repository, new_victim: &NewVictim, should_color: bool) -> miette::Result<()>
+{
+	let full_name = FullName::try_from(worktree::split(new_victim.name.as_bstr()).1)
+		.into_diagnostic()
+		.with_context(|| format!("while validating ref name {}", new_victim.name))?;
+	let short = full_name.as_ref().shorten();
+
+	let same_short = repo.find_references_by_short_name(short)?;
+	if same_short.is_empty() {
+		return Ok(());
+	}
+
+	let existing_descs: Vec<String> = same_short
+		.iter()
+		.map(|existing_ref| {
+			format!(
+				"{} at {}",
+				existing_ref.name().as_bstr().style_as_ref_if(should_color),
+				peeled_hex_or_placeholder(existing_ref).style_as_commit_if(should_color),
+			)
+		})
+		.collect();
+
+	eprintln!(
+		"{} refusing to create ref {} whose short name '{}' is already used by: {}; \
+		pass --allow-ambiguous to override",
+		"error:".style_as_error_if(should_color),
+		new_victim.name.as_bstr().style_as_ref_if(should_color),
+		short.style_as_ref_if(should_color),
+		existing_descs.join(", "),
+	);
+
+	std::process::exit(3);
+}
+
+/// Finds the repository to work on the way git does: honoring -C, --git-dir, --work-tree,
+/// and GIT_DIR and GIT_WORK_TREE, and otherwise searching upwards from the current directory.
+fn open_repo(args: &GitPointCmd) -> miette::Result<Repository>
+{
+	for dir in &args.directory {
+		env::set_current_dir(dir)
+			.into_diagnostic()
+			.with_context(|| format!("while changing directory to {}", dir.display()))?;
+	}
+
+	// Git itself implements these by setting the environment variables,
+	// and so does gix's discovery read them.
+	if let Some(git_dir) = &args.git_dir {
+		env::set_var("GIT_DIR", git_dir);
+	}
+	if let Some(work_tree) = &args.work_tree {
+		env::set_var("GIT_WORK_TREE", work_tree);
+	}
+
+	let cwd: PathBuf = env::current_dir().into_diagnostic()?;
+
+	let repo: Repository = gix::ThreadSafeRepository::discover_with_environment_overrides(&cwd)
+		.into_diagnostic()
+		.with_context(|| format!("while finding git repo from {}", cwd.display()))?
+		.to_thread_local();
+
+	debug!(
+		"using git dir {} with worktree {:?}",
+		repo.git_dir().display(),
+		repo.workdir().map(|dir| dir.display().to_string()),
+	);
+
+	Ok(repo)
 }
 
 fn main() -> miette::Result<()>
@@ -475,31 +1794,82 @@ fn main() -> miette::Result<()>
 	let from = args.from.take().unwrap();
 	let to = args.to.take().unwrap();
 
-	let cwd: PathBuf = env::current_dir().into_diagnostic()?;
+	let repo = open_repo(&args)?;
 
-	let repo: Repository = gix::open(&cwd)
-		.into_diagnostic()
-		.with_context(|| format!("while opening git repo in {}", cwd.display()))?;
+	// Ambiguous names can be resolved by asking, unless we're not supposed to ask anything.
+	let can_pick = !args.yes && confirm::can_ask();
 
+	// Only set for --remote-head.
+	let mut remote_head: Option<FullName> = None;
+
+	// A bare --new is `Some(None)`, and infers the kind from <FROM>.
 	let victim = match &args.new {
-		Some(kind) => {
+		Some(maybe_kind) => {
+			let (kind, short) = match maybe_kind {
+				Some(kind) => (*kind, BString::from(from.clone())),
+				None => NewRefKind::infer(&repo, from.as_bytes().as_bstr())?,
+			};
 			debug!("requested to create new {} ref", kind.to_prefix());
 
+			// Validate the name first, so we never go looking for a ref that can't exist.
+			let new_victim = NewVictim::new(kind, short)?;
+
+			if maybe_kind.is_none() {
+				eprintln!(
+					"Inferred {} {} from '{}'",
+					kind.name(),
+					new_victim.name.as_bstr().style_as_ref_if(should_color),
+					from,
+				);
+			}
+
+			if kind == NewRefKind::RemoteBranch {
+				// Make sure this is a ref `git fetch` would actually manage, so we don't leave
+				// an orphan that `git fetch --prune` will never clean up.
+				let remote_name = remote::validate_remote_branch(&repo, new_victim.short.as_bstr())?;
+
+				if args.remote_head {
+					let head_name: BString = [
+						RefCategory::RemoteBranch.prefix().as_bytes(),
+						remote_name.as_bytes(),
+						b"/HEAD",
+					].concat().into();
+
+					if let Ok(Some(existing_head)) = repo.try_find_reference(head_name.as_bstr()) {
+						eprintln!(
+							"{} refusing to create ref {} which already exists at {}",
+							"error:".style_as_error_if(should_color),
+							head_name.as_bstr().style_as_ref_if(should_color),
+							peeled_hex_or_placeholder(&existing_head).style_as_commit_if(should_color),
+						);
+
+						std::process::exit(2);
+					}
+
+					remote_head = Some(FullName::try_from(head_name).into_diagnostic()?);
+				}
+			} else if args.remote_head {
+				return Err(miette::miette!(
+					"--remote-head can only be used when creating a remote-branch ref, not a {}",
+					kind.name(),
+				));
+			}
+
+			// Git stores refs as paths, so `refs/heads/feature` and `refs/heads/feature/x`
+			// can't both exist. This function will exit the process if that would happen.
+			// Per-worktree refs of another worktree have to be checked against that worktree.
+			// This comes first, since looking up a name under an existing ref fails.
+			let owner_repo = owner_repo(&repo, new_victim.name.as_bstr())?;
+			check_ref_conflicts(&owner_repo, &new_victim, should_color);
+
 			// Disallow if the ref already exists, though we will
 			// enforce this at the transaction level below as well.
-			let maybe_existing = repo.try_find_reference(&from)
-				.tap_err(|e| warn!("ignoring error checking if {} already exists: {}", from, e));
+			let maybe_existing = repo.try_find_reference(new_victim.name.as_bstr())
+				.tap_err(|e| warn!("ignoring error checking if {} already exists: {}", new_victim.name, e));
 
 			if let Ok(Some(existing_ref)) = maybe_existing {
 
-				let existing_id = existing_ref
-					.clone()
-					.into_fully_peeled_id()
-					.map(|peeled| peeled.to_hex().to_string())
-					.unwrap_or_else(|e| {
-						warn!("error resolving existing ref {}: {}", existing_ref.name().as_bstr(), e);
-						String::from("<could not resolve>")
-					});
+				let existing_id = peeled_hex_or_placeholder(&existing_ref);
 
 				eprintln!(
 					"{} refusing to create ref {} which already exists at {}",
@@ -511,13 +1881,21 @@ fn main() -> miette::Result<()>
 				std::process::exit(2);
 			}
 
-			Victim::New(NewVictim::new(*kind, BString::from(from.clone())))
+			if !args.allow_ambiguous {
+				// We refuse to work with ambiguous refs, so we shouldn't create them either.
+				// This function will exit the process if we would.
+				check_new_ambiguity(&owner_repo, &new_victim, should_color)?;
+			}
+
+			Victim::New(new_victim)
 		},
 		None => {
-			let reference = repo
-				.find_reference(&from)
+			let mut from = from.clone();
+			let mut reference = repo
+				.try_find_reference(&from)
 				.into_diagnostic()
-				.with_context(|| format!("while finding reference '{}'", &from))?;
+				.with_context(|| format!("while finding reference '{}'", &from))?
+				.ok_or_else(|| suggest::missing_ref(&repo, from.as_bytes().as_bstr(), !args.check))?;
 
 			// Make sure args.from is not ambiguous and can only refer to one ref.
 			// gix does not have a convenient "repo.find_references()", so what we do here
@@ -526,61 +1904,173 @@ fn main() -> miette::Result<()>
 			let from_bytes: &BStr = from.as_bytes().into();
 			let ambiguous_refs = repo.find_ambiguous_references(from_bytes)?;
             if let MaybeAmbigRef::Ambiguous { ref requested, ref possible } = ambiguous_refs {
-
-				eprintln!(
-					"{} refspec '{}' is ambiguous and must be qualified; \
-                    could be any of: {}",
-					"error:".style_as_error_if(should_color),
-                    &requested.style_as_ref_if(should_color),
-					bstr::join(", ", possible).as_bstr()
-				);
-
-				std::process::exit(3);
+				let candidates = Candidate::resolve_all(&repo, possible);
+				let chosen = if can_pick {
+					pick_ambiguous(&repo, requested.as_bstr(), &candidates, should_color)?
+				} else {
+					None
+				};
+				let Some(chosen) = chosen else {
+					eprintln!(
+						"{} refspec '{}' is ambiguous and must be qualified; could be any of:",
+						"error:".style_as_error_if(should_color),
+						&requested.style_as_ref_if(should_color),
+					);
+					print_ambiguity_details(
+						&repo,
+						&candidates,
+						|qualified| command_replacing(&from, &qualified.to_str_lossy(), false),
+						should_color,
+					);
+
+					std::process::exit(3);
+				};
+
+				reference = repo
+					.find_reference(chosen.as_bstr())
+					.into_diagnostic()
+					.with_context(|| format!("while finding reference '{}'", chosen))?;
+				from = chosen.to_string();
 			}
 
-			if !args.allow_worktree {
+			if !args.allow_worktree && !args.check {
 				// Check if the victim *ref* is checked out anywhere.
 				// This function will exit the process if so.
 				// Technically this is a TOC/TOU race condition, but if someone else is
 				// concurrently mutating this repo then we're fucked anyway.
-				check_worktrees(&repo, &reference, should_color);
+				check_worktrees(&repo, &reference, args.update_worktree.is_some(), should_color);
 			}
 
 			Victim::Known(KnownVictim::from(BString::from(from.clone()), reference)?)
 		},
 	};
 
-	let target = TargetRev::from(&repo, BString::from(to), should_color)?;
-
-    let reflog_msg = match victim {
-        Victim::Known(ref victim_ref) => format!(
-            "git-point: updating {} from {} to {}",
-			victim_ref.name.as_bstr(),
-            victim_ref.resolved_id,
-            target.resolved_id,
-        ),
-		Victim::New(ref name) => format!(
-			"git-point: created {} from {}",
-			name.name.as_bstr(),
-			target.resolved_id
-		),
-    };
+	let target = TargetRev::from(&repo, BString::from(to), can_pick, should_color)?;
+
+	if let (Victim::Known(known), true) = (&victim, args.check) {
+		// This function always exits the process.
+		check_points_to(&repo, known, &target, should_color)?;
+	}
+
+	// What --expect says the victim must point to when we write it, and what it stores then.
+	let expected = match (&victim, &args.expect) {
+		(Victim::Known(known), Some(expect)) => {
+			// Resolved like <TO>, so an ambiguous name is picked or refused the same way.
+			let expected_id = TargetRev::from(&repo, BString::from(expect.as_str()), can_pick, should_color)
+				.with_context(|| format!("while resolving --expect {}", expect))?
+				.resolved_id
+				.detach();
+
+			// This function will exit the process if it's moved.
+			let stored = check_not_moved(&repo, known.name.as_ref(), expected_id, should_color)?;
+
+			Some((expected_id, stored))
+		},
+		_ => None,
+	};
+
+	if let Victim::Known(known) = &victim {
+		if known.resolved_id == target.resolved_id {
+			// Nothing to do, so don't write anything, not even a reflog entry.
+			eprintln!(
+				"{refname} is already at {id} ({msg})",
+				refname = known.name.as_bstr().style_as_ref_if(should_color),
+				id = known.resolved_id.shorten_or_id().style_as_commit_if(should_color),
+				msg = known.summary.as_bstr(),
+			);
+
+			std::process::exit(if args.exit_code { 7 } else { 0 });
+		}
+	}
+
+	if let Victim::Known(known) = &victim {
+		if !args.allow_unrelated {
+			// This function will exit the process if `target` is from some other history.
+			check_related(&repo, known, &target, should_color)?;
+		}
+	}
+
+	// Work out what the ref gains and loses before we move it, while `victim` still describes it.
+	let movement = match &victim {
+		Victim::Known(known) => {
+			let old = known.resolved_id.detach();
+			let new = target.resolved_id.detach();
+			Some((
+				history::only_in_ids(&repo, new, old, usize::MAX)?,
+				history::only_in_ids(&repo, old, new, usize::MAX)?,
+			))
+		},
+		Victim::New(_) => None,
+	};
+
+	if !args.force_protected {
+		// This function will exit the process if `victim` is protected.
+		check_protected(&repo, &victim, movement.as_ref(), should_color);
+	}
+
+	if let Victim::Known(known) = &victim {
+		if !args.retag && known.name.category() == Some(RefCategory::Tag) {
+			// This function will exit the process.
+			refuse_retag(&repo, known, &target, should_color)?;
+		}
+	}
+
+	let worktree_updates = match (&victim, args.update_worktree) {
+		(Victim::Known(known), Some(mode)) => plan_worktree_updates(&repo, known, &target, mode, should_color)?,
+		_ => Vec::new(),
+	};
+
+	let upstream = match (&victim, &target.symref) {
+		(Victim::New(new), Some(start)) if new.kind == NewRefKind::Branch && !args.no_track => {
+			let mode = track::AutoSetupMerge::from_config(&repo);
+			track::upstream_for(&repo, new.short.as_bstr(), start.as_ref(), mode, args.track)
+		},
+		_ => None,
+	};
+	if args.track && upstream.is_none() {
+		return Err(miette::miette!(
+			help = "--track needs --new=branch, and a <TO> that is just the name of a branch",
+			"cannot set up tracking for {} from '{}'",
+			victim.name_bstr(),
+			target.revspec,
+		));
+	}
+
+	let reason = args
+		.message
+		.clone()
+		.or_else(|| env::var(reflog::REASON_ENV).ok())
+		.filter(|reason| !reason.is_empty());
+	let reflog_msg = reflog::message(&repo, &reflog::Entry {
+		name: victim.name_bstr(),
+		old: match &victim {
+			Victim::Known(known) => Some(known.resolved_id.detach()),
+			Victim::New(_new) => None,
+		},
+		new: target.resolved_id.detach(),
+		reason: reason.as_deref(),
+	});
 
 	let transaction = RefEdit {
 		change: Change::Update {
 			log: LogChange {
 				mode: RefLog::AndReference,
 				force_create_reflog: false,
-				message: BString::from(reflog_msg.clone()),
+				message: reflog_msg,
 			},
-			expected: match &victim {
-				Victim::Known(victim_ref) => PreviousValue::MustExistAndMatch(victim_ref.transaction_target()),
-				Victim::New(_new) => PreviousValue::MustNotExist,
+			// With --expect, only write if it still stores what it did when checked against that,
+			// rather than what we read before.
+			expected: match (&victim, &expected) {
+				(Victim::Known(_victim_ref), Some((_expected_id, stored))) => PreviousValue::MustExistAndMatch(stored.clone()),
+				(Victim::Known(victim_ref), None) => PreviousValue::MustExistAndMatch(victim_ref.transaction_target()),
+				(Victim::New(_new), _) => PreviousValue::MustNotExist,
 			},
 			new: target.transaction_target(),
 		},
 		name: {
-			FullName::try_from(victim.name_bstr()).unwrap()
+			FullName::try_from(victim.name_bstr())
+				.into_diagnostic()
+				.with_context(|| format!("while validating ref name {}", victim.name_bstr()))?
 		},
 		deref: false,
 	};
@@ -596,7 +2086,53 @@ fn main() -> miette::Result<()>
 		}
 	}
 
-	let _edits = repo.edit_reference(transaction.clone())
+	let mut edits = vec![transaction.clone()];
+	if let Some(head_name) = &remote_head {
+		edits.push(RefEdit {
+			change: Change::Update {
+				log: LogChange {
+					mode: RefLog::AndReference,
+					force_create_reflog: false,
+					message: format!(
+						"git-point: created {} pointing to {}",
+						head_name.as_bstr(),
+						victim.name_bstr(),
+					).into(),
+				},
+				expected: PreviousValue::MustNotExist,
+				new: Target::Symbolic(transaction.name.clone()),
+			},
+			name: head_name.clone(),
+			deref: false,
+		});
+	}
+
+	// This function will exit the process if the change is declined.
+	let previewed = !args.yes
+		&& confirm_change(&repo, &args, &victim, &target, movement.as_ref(), &worktree_updates, should_color)?;
+
+	// Write the tracking config up to the point of replacing the file before touching the ref,
+	// so that a problem with it can't leave a half set up branch behind.
+	let pending_upstream = match (&victim, &upstream) {
+		(Victim::New(new), Some(upstream)) => Some(
+			track::prepare_upstream(&repo, new.short.as_bstr(), upstream)
+				.with_context(|| format!("while setting upstream of {}", new.name))?
+		),
+		_ => None,
+	};
+
+	let edited = repo.edit_references(edits);
+	if let (Err(e), Victim::Known(known)) = (&edited, &victim) {
+		debug!("updating {} failed, checking if it moved: {}", known.name.as_bstr(), e);
+		// Someone else may have moved it since we looked.
+		// This function will exit the process if so.
+		let expected_id = expected
+			.as_ref()
+			.map_or(known.resolved_id.detach(), |(expected_id, _stored)| *expected_id);
+		check_not_moved(&repo, known.name.as_ref(), expected_id, should_color)?;
+	}
+
+	let _edits = edited
 		.into_diagnostic()
 		.with_context(|| match &victim {
 			Victim::Known(_know) => format!(
@@ -612,14 +2148,31 @@ fn main() -> miette::Result<()>
 		})?;
 
 	match &victim {
-		Victim::Known(known) => eprintln!(
-			"Updated {refname} from {previd} ({prevmsg}) to {newid} ({newmsg})",
-			refname = known.name.as_bstr().style_as_ref_if(should_color),
-			previd = known.resolved_id.shorten_or_id().style_as_commit_if(should_color),
-			prevmsg = known.summary.as_bstr(),
-			newid = target.resolved_id.shorten_or_id().style_as_commit_if(should_color),
-			newmsg = target.summary.as_bstr(),
-		),
+		Victim::Known(known) => {
+			let (gained, lost) = movement.as_ref().expect("movement is computed for known victims");
+
+			eprintln!(
+				"Updated {refname} from {previd} ({prevmsg}) to {newid} ({newmsg}) [{movement}]",
+				refname = known.name.as_bstr().style_as_ref_if(should_color),
+				previd = known.resolved_id.shorten_or_id().style_as_commit_if(should_color),
+				prevmsg = known.summary.as_bstr(),
+				newid = target.resolved_id.shorten_or_id().style_as_commit_if(should_color),
+				newmsg = target.summary.as_bstr(),
+				movement = history::describe_move(gained.len(), lost.len()),
+			);
+
+			if let Some(tag_object) = known.tag_object() {
+				eprintln!(
+					"{} was an annotated tag; it's now a lightweight tag, and its tag object {} is no longer referenced",
+					known.short.as_bstr().style_as_ref_if(should_color),
+					tag_object.to_hex_with_len(7).style_as_commit_if(should_color),
+				);
+			}
+
+			if !previewed {
+				print_previews(&repo, &args, known, &target, gained, lost, should_color)?;
+			}
+		},
 		Victim::New(new) => eprintln!(
 			"Created {refname} at {target_id} ({msg})",
 			refname = new.name.as_bstr().style_as_ref_if(should_color),
@@ -628,5 +2181,39 @@ fn main() -> miette::Result<()>
 		)
 	}
 
+	apply_worktree_updates(&worktree_updates)?;
+
+	if let (Some(pending), Some(upstream)) = (pending_upstream, &upstream) {
+		// victim is always new here.
+		let Victim::New(new) = &victim else { unreachable!() };
+
+		if let Err(e) = pending.commit() {
+			return Err(miette::miette!(
+				help = format!(
+					"run `git branch --set-upstream-to={} {}` to finish setting it up",
+					upstream.display_name(),
+					new.short,
+				),
+				"{} was created, but setting up its upstream failed: {}",
+				new.name,
+				e.chain().map(ToString::to_string).collect::<Vec<_>>().join(": "),
+			));
+		}
+
+		eprintln!(
+			"Set up {} to track {}",
+			new.short.as_bstr().style_as_ref_if(should_color),
+			upstream.display_name().style_as_ref_if(should_color),
+		);
+	}
+
+	if let Some(head_name) = &remote_head {
+		eprintln!(
+			"Created {} pointing to {}",
+			head_name.as_bstr().style_as_ref_if(should_color),
+			victim.name_bstr().style_as_ref_if(should_color),
+		);
+	}
+
 	Ok(())
 }
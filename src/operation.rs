@@ -0,0 +1,79 @@
+//! Refs in use by an in-progress rebase, bisect, merge, cherry-pick, etc, which git itself
+//! refuses to move out from under them.
+
+use std::path::Path;
+
+use bstr::{BStr, BString, ByteSlice};
+use gix::state::InProgress;
+use gix::Repository;
+
+#[allow(unused)]
+use log::{trace, debug, warn, info, error};
+
+/// How an operation in progress is described to the user, e.g. "a rebase".
+pub fn describe(operation: &InProgress) -> &'static str
+{
+	use InProgress::*;
+	match operation {
+		ApplyMailbox => "a `git am`",
+		ApplyMailboxRebase | Rebase | RebaseInteractive => "a rebase",
+		Bisect => "a bisect",
+		CherryPick | CherryPickSequence => "a cherry-pick",
+		Merge => "a merge",
+		Revert | RevertSequence => "a revert",
+	}
+}
+
+/// Reads a file from a git dir which contains a single line, if it exists.
+fn read_state_file(git_dir: &Path, name: &str) -> Option<BString>
+{
+	let path = git_dir.join(name);
+	let contents = match std::fs::read(&path) {
+		Ok(contents) => contents,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+		Err(e) => {
+			warn!("ignoring error reading {}: {}", path.display(), e);
+			return None;
+		},
+	};
+
+	Some(contents.trim_end().into())
+}
+
+/// The operation in progress in `tree_repo`'s worktree, if that operation is working on
+/// `name` even though HEAD may not point to it.
+///
+/// `name` is as seen from inside `tree_repo`. `is_owner` says whether it names one of
+/// `tree_repo`'s own per-worktree refs (like `refs/bisect/*`) rather than another worktree's.
+pub fn using_ref(tree_repo: &Repository, name: &BStr, is_owner: bool) -> Option<InProgress>
+{
+	let operation = tree_repo.state()?;
+	let git_dir = tree_repo.git_dir();
+
+	let in_use = match operation {
+		InProgress::Rebase | InProgress::RebaseInteractive | InProgress::ApplyMailboxRebase => {
+			// HEAD is detached while rebasing, and the branch being rebased is only
+			// recorded here, to be updated once the rebase finishes.
+			["rebase-merge/head-name", "rebase-apply/head-name"]
+				.into_iter()
+				.filter_map(|file| read_state_file(git_dir, file))
+				.any(|head_name| head_name == name)
+		},
+		InProgress::Bisect => {
+			// BISECT_START holds the short name of the branch to go back to after
+			// `git bisect reset`, or a commit ID if HEAD was detached.
+			let start_branch = read_state_file(git_dir, "BISECT_START")
+				.map(|start| BString::from([b"refs/heads/".as_slice(), start.as_bytes()].concat()));
+
+			start_branch.as_ref().is_some_and(|start| start == name)
+				|| (is_owner && name.starts_with(b"refs/bisect/"))
+		},
+		_ => false,
+	};
+
+	if in_use {
+		debug!("{} is in use by {} in {}", name, describe(&operation), git_dir.display());
+	}
+
+	in_use.then_some(operation)
+}
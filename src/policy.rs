@@ -0,0 +1,126 @@
+//! Config entries which apply to the refs matching a pattern, like `point.protect` and
+//! `point.confirm`.
+//!
+//! Each value is a ref pattern, optionally preceded by a condition and a colon,
+//! e.g. `refs/heads/main`, `ff-only:refs/heads/main`, or `rewind:refs/heads/*`.
+//! Colons can't appear in ref names, so this is never ambiguous.
+
+use bstr::{BStr, BString, ByteSlice};
+use gix::glob::wildmatch;
+use gix::Repository;
+
+#[allow(unused)]
+use log::{trace, debug, warn, info, error};
+
+/// The conditions one config key's entries can have.
+pub trait Condition: Copy + Eq + 'static
+{
+	/// The condition of an entry without one.
+	const DEFAULT: Self;
+
+	/// Every condition, by how it's written in config.
+	const NAMES: &'static [(&'static str, Self)];
+
+	fn name(self) -> &'static str
+	{
+		Self::NAMES
+			.iter()
+			.find(|&&(_name, condition)| condition == self)
+			.map(|&(name, _condition)| name)
+			.expect("every condition has a name")
+	}
+}
+
+/// One entry of a config key like `point.protect`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RefPolicy<C>
+{
+	/// The full ref name pattern, where `*` matches anything, including slashes.
+	pub pattern: BString,
+	pub condition: C,
+}
+
+impl<C: Condition> RefPolicy<C>
+{
+	fn parse(value: &BStr) -> Option<Self>
+	{
+		let Some((condition_name, pattern)) = value.split_once_str(":") else {
+			return Some(Self { pattern: value.trim().into(), condition: C::DEFAULT });
+		};
+
+		let condition_name = condition_name.trim();
+		let &(_name, condition) = C::NAMES
+			.iter()
+			.find(|(name, _condition)| name.as_bytes() == condition_name)?;
+
+		Some(Self { pattern: pattern.trim().into(), condition })
+	}
+
+	pub fn matches(&self, name: &BStr) -> bool
+	{
+		wildmatch(self.pattern.as_bstr(), name, wildmatch::Mode::empty())
+	}
+
+	/// How this entry would be written in config, for messages.
+	pub fn display(&self) -> BString
+	{
+		if self.condition == C::DEFAULT {
+			return self.pattern.clone();
+		}
+
+		[self.condition.name().as_bytes(), b":", self.pattern.as_bytes()].concat().into()
+	}
+}
+
+/// Every valid entry of `key` in `repo`'s config.
+pub fn from_config<C: Condition>(repo: &Repository, key: &str) -> Vec<RefPolicy<C>>
+{
+	let config = repo.config_snapshot();
+	let Some(values) = config.plumbing().strings(key) else {
+		return Vec::new();
+	};
+
+	values
+		.iter()
+		.filter_map(|value| {
+			let policy = RefPolicy::parse(value.as_ref());
+			if policy.is_none() {
+				let expected: Vec<String> = C::NAMES
+					.iter()
+					.map(|(name, _condition)| format!("'{}'", name))
+					.collect();
+				warn!(
+					"ignoring {} '{}' with unknown condition; expected one of {}",
+					key,
+					value,
+					expected.join(", "),
+				);
+			}
+			policy
+		})
+		.collect()
+}
+
+/// What we're about to do to a ref.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Change
+{
+	Create,
+
+	/// `rewinds` if it no longer contains commits it did before, and `checked_out` if it's
+	/// checked out in a worktree.
+	Move { rewinds: bool, checked_out: bool },
+}
+
+/// The first entry in `policies` which matches `name` and `applies` to its condition, if any.
+pub fn first_applying<'p, C: Condition>(
+	policies: &'p [RefPolicy<C>],
+	name: &BStr,
+	applies: impl Fn(C) -> bool,
+) -> Option<&'p RefPolicy<C>>
+{
+	policies
+		.iter()
+		.filter(|policy| policy.matches(name))
+		.find(|policy| applies(policy.condition))
+}
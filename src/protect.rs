@@ -0,0 +1,56 @@
+//! Local policy protecting refs from being created or moved, read from `point.protect`,
+//! e.g. `refs/heads/main`, `ff-only:refs/heads/main`, or `create-only:refs/tags/*`.
+
+use bstr::BStr;
+use gix::Repository;
+
+#[allow(unused)]
+use log::{trace, debug, warn, info, error};
+
+use crate::policy::{self, Change, Condition, RefPolicy};
+
+/// What a protected pattern still allows.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Rule
+{
+	/// No rule given: matching refs may be neither created nor moved.
+	Deny,
+
+	/// `ff-only`: matching refs may be created, and moved forward to commits that contain
+	/// the one they point to now.
+	FastForwardOnly,
+
+	/// `create-only`: matching refs may be created, but never moved once they exist.
+	CreateOnly,
+}
+
+impl Condition for Rule
+{
+	const DEFAULT: Self = Self::Deny;
+
+	const NAMES: &'static [(&'static str, Self)] = &[
+		("deny", Self::Deny),
+		("ff-only", Self::FastForwardOnly),
+		("create-only", Self::CreateOnly),
+	];
+}
+
+/// One `point.protect` entry.
+pub type Protection = RefPolicy<Rule>;
+
+/// Every valid `point.protect` entry in `repo`'s config.
+pub fn from_config(repo: &Repository) -> Vec<Protection>
+{
+	policy::from_config(repo, "point.protect")
+}
+
+/// The first protection matching `name` that forbids `change`, if any.
+pub fn violated<'p>(protections: &'p [Protection], name: &BStr, change: Change) -> Option<&'p Protection>
+{
+	policy::first_applying(protections, name, |rule| match (rule, change) {
+		(Rule::Deny, _) => true,
+		(Rule::CreateOnly | Rule::FastForwardOnly, Change::Create) => false,
+		(Rule::CreateOnly, Change::Move { .. }) => true,
+		(Rule::FastForwardOnly, Change::Move { rewinds, .. }) => rewinds,
+	})
+}
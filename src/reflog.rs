@@ -0,0 +1,127 @@
+//! The messages git-point writes to the reflogs of the refs it changes, which can be customized
+//! with a template in `point.reflogMessage`.
+//!
+//! Templates can use these placeholders:
+//! - `{ref}`: the full name of the ref, e.g. `refs/heads/main`
+//! - `{old}`: the commit it pointed to before, or nothing if it was created
+//! - `{new}`: the commit it points to now
+//! - `{change}`: the default description, e.g. `updating refs/heads/main from <old> to <new>`
+//! - `{reason}`: the reason given with `--message`, or `GIT_POINT_REASON`
+//! - `{caller}`: the command line of the process which ran git-point, where it can be found
+
+use bstr::{BStr, BString, ByteSlice};
+use gix::{ObjectId, Repository};
+
+#[allow(unused)]
+use log::{trace, debug, warn, info, error};
+
+/// Environment variable giving a reason when `--message` isn't, e.g. set by a script
+/// which runs git-point.
+pub const REASON_ENV: &str = "GIT_POINT_REASON";
+
+/// The most of our caller's command line we record, since it can be an entire script.
+const MAX_CALLER_LEN: usize = 200;
+
+/// A change to a ref, to describe in its reflog.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Entry<'a>
+{
+	pub name: &'a BStr,
+	/// `None` if the ref is being created.
+	pub old: Option<ObjectId>,
+	pub new: ObjectId,
+	pub reason: Option<&'a str>,
+}
+
+impl Entry<'_>
+{
+	fn change(&self) -> String
+	{
+		match self.old {
+			Some(old) => format!("updating {} from {} to {}", self.name, old, self.new),
+			None => format!("created {} from {}", self.name, self.new),
+		}
+	}
+}
+
+/// The command line of our parent process, if we can find it.
+#[cfg(target_os = "linux")]
+fn caller() -> Option<String>
+{
+	let parent = std::os::unix::process::parent_id();
+	let cmdline = std::fs::read(format!("/proc/{}/cmdline", parent))
+		.map_err(|e| debug!("cannot read command line of parent process {}: {}", parent, e))
+		.ok()?;
+
+	let args: Vec<String> = cmdline
+		.split_str(b"\0")
+		.filter(|arg| !arg.is_empty())
+		.map(|arg| arg.to_str_lossy().into_owned())
+		.collect();
+
+	let mut caller = args.join(" ");
+	if let Some((cut, _c)) = caller.char_indices().nth(MAX_CALLER_LEN) {
+		caller.truncate(cut);
+		caller.push_str("...");
+	}
+
+	Some(caller)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn caller() -> Option<String>
+{
+	None
+}
+
+/// Expands the placeholders in `template` for `entry`, leaving anything else as-is.
+fn expand(template: &str, entry: &Entry) -> String
+{
+	let mut message = String::with_capacity(template.len());
+	let mut rest = template;
+	while let Some(start) = rest.find('{') {
+		message.push_str(&rest[..start]);
+		rest = &rest[start..];
+
+		let end = rest.find('}').map(|end| end + 1).unwrap_or(rest.len());
+		let value = match &rest[..end] {
+			"{ref}" => Some(entry.name.to_string()),
+			"{old}" => Some(entry.old.map(|old| old.to_string()).unwrap_or_default()),
+			"{new}" => Some(entry.new.to_string()),
+			"{change}" => Some(entry.change()),
+			"{reason}" => Some(entry.reason.unwrap_or_default().to_owned()),
+			"{caller}" => Some(caller().unwrap_or_default()),
+			_ => None,
+		};
+
+		match value {
+			Some(value) => {
+				message.push_str(&value);
+				rest = &rest[end..];
+			},
+			None => {
+				message.push('{');
+				rest = &rest[1..];
+			},
+		}
+	}
+	message.push_str(rest);
+
+	message
+}
+
+/// The reflog message for `entry`, from the `point.reflogMessage` template if there is one.
+pub fn message(repo: &Repository, entry: &Entry) -> BString
+{
+	let config = repo.config_snapshot();
+	let message = match config.string("point.reflogMessage") {
+		Some(template) => expand(&template.to_str_lossy(), entry),
+		None => match entry.reason {
+			Some(reason) => format!("git-point: {}: {}", entry.change(), reason),
+			None => format!("git-point: {}", entry.change()),
+		},
+	};
+
+	// Each reflog entry is one line.
+	message.replace(['\n', '\r'], " ").trim().into()
+}
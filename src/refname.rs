@@ -0,0 +1,163 @@
+//! Validation of ref names we are asked to create, following git-check-ref-format(1).
+
+use std::ops::Range;
+
+use bstr::{BStr, BString, ByteSlice};
+use miette::LabeledSpan;
+
+/// Why a ref name was rejected, and where in the name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FormatError
+{
+	/// Byte range of the offending part of the name.
+	pub span: Range<usize>,
+
+	/// Human readable description of the broken rule.
+	pub reason: &'static str,
+}
+
+impl FormatError
+{
+	fn new(span: Range<usize>, reason: &'static str) -> Self
+	{
+		Self { span, reason }
+	}
+}
+
+fn is_forbidden_byte(b: u8) -> bool
+{
+	b.is_ascii_control() || matches!(b, b' ' | b'~' | b'^' | b':' | b'?' | b'*' | b'[' | b'\\')
+}
+
+/// Checks a fully qualified ref name against the rules of git-check-ref-format(1),
+/// returning the first rule it breaks.
+///
+/// One-level names (like `HEAD`) are allowed, as they are by `git update-ref`.
+pub fn check_format(name: &BStr) -> Result<(), FormatError>
+{
+	if name.is_empty() {
+		return Err(FormatError::new(0..0, "ref names cannot be empty"));
+	}
+	if name == "@" {
+		return Err(FormatError::new(0..1, "'@' alone is not a valid ref name"));
+	}
+
+	for (i, &b) in name.iter().enumerate() {
+		let rest = &name[i..];
+		if is_forbidden_byte(b) {
+			return Err(FormatError::new(
+				i..(i + 1),
+				"ref names cannot contain spaces, control characters, or any of ~^:?*[\\",
+			));
+		}
+		if rest.starts_with(b"..") {
+			return Err(FormatError::new(i..(i + 2), "ref names cannot contain '..'"));
+		}
+		if rest.starts_with(b"//") {
+			return Err(FormatError::new(i..(i + 2), "ref names cannot contain consecutive slashes"));
+		}
+		if rest.starts_with(b"@{") {
+			return Err(FormatError::new(i..(i + 2), "ref names cannot contain '@{'"));
+		}
+	}
+
+	if name.starts_with(b"/") {
+		return Err(FormatError::new(0..1, "ref names cannot begin with '/'"));
+	}
+	if name.ends_with(b"/") {
+		return Err(FormatError::new((name.len() - 1)..name.len(), "ref names cannot end with '/'"));
+	}
+	if name.ends_with(b".") {
+		return Err(FormatError::new((name.len() - 1)..name.len(), "ref names cannot end with '.'"));
+	}
+
+	let mut offset = 0;
+	for component in name.split_str("/") {
+		if component.starts_with(b".") {
+			return Err(FormatError::new(offset..(offset + 1), "ref name components cannot begin with '.'"));
+		}
+		if component.ends_with(b".lock") {
+			let end = offset + component.len();
+			return Err(FormatError::new((end - 5)..end, "ref name components cannot end with '.lock'"));
+		}
+		offset += component.len() + 1;
+	}
+
+	Ok(())
+}
+
+/// Makes a best effort at turning `name` into something [check_format] accepts,
+/// by replacing or dropping whatever is in the way.
+///
+/// Returns `None` if nothing sensible is left.
+pub fn suggest(name: &BStr) -> Option<BString>
+{
+	let mut replaced: Vec<u8> = name
+		.iter()
+		.map(|&b| if is_forbidden_byte(b) { b'-' } else { b })
+		.collect();
+
+	while let Some(pos) = replaced.find("..") {
+		replaced.remove(pos);
+	}
+	while let Some(pos) = replaced.find("@{") {
+		replaced[pos + 1] = b'-';
+	}
+
+	let components: Vec<&[u8]> = replaced
+		.split_str("/")
+		.map(|mut component| {
+			while let Some(stripped) = component.strip_prefix(b".") {
+				component = stripped;
+			}
+			while let Some(stripped) = component.strip_suffix(b".lock") {
+				component = stripped;
+			}
+			component
+		})
+		.filter(|component| !component.is_empty())
+		.collect();
+
+	let mut suggestion = BString::from(bstr::join("/", components));
+	while suggestion.ends_with(b".") {
+		suggestion.pop();
+	}
+
+	if suggestion.is_empty() || suggestion == "@" {
+		return None;
+	}
+
+	Some(suggestion)
+}
+
+/// Validates `prefix` + `short` as the name of a new ref.
+///
+/// On failure, the returned diagnostic labels the offending part of the name
+/// and suggests a corrected one if we can come up with one.
+pub fn validate_new(prefix: &BStr, short: &BStr) -> miette::Result<()>
+{
+	let full: BString = prefix.iter().chain(short.iter()).copied().collect();
+
+	let Err(e) = check_format(full.as_bstr()) else {
+		return Ok(());
+	};
+
+	let suggestion = suggest(short).filter(|suggestion| {
+		let full: BString = prefix.iter().chain(suggestion.iter()).copied().collect();
+		check_format(full.as_bstr()).is_ok()
+	});
+
+	let help = match suggestion {
+		Some(suggestion) => format!("did you mean '{}'?", suggestion),
+		None => String::from("see git-check-ref-format(1) for the rules ref names must follow"),
+	};
+
+	let report = miette::miette!(
+		labels = vec![LabeledSpan::new_with_span(Some(e.reason.to_string()), e.span)],
+		help = help,
+		"'{}' is not a valid ref name",
+		full,
+	);
+
+	Err(report.with_source_code(full.to_string()))
+}
@@ -0,0 +1,109 @@
+//! Sanity checks for new remote-tracking refs.
+
+use bstr::{BStr, BString, ByteSlice};
+use gix::remote::Direction;
+use gix::Repository;
+
+#[allow(unused)]
+use log::{trace, debug, warn, info, error};
+
+use crate::suggest;
+
+/// Whether `name` matches the refspec pattern `pattern`, which may contain at most one `*`.
+fn matches_pattern(pattern: &BStr, name: &BStr) -> bool
+{
+	match pattern.split_once_str("*") {
+		Some((before, after)) => {
+			name.len() >= before.len() + after.len()
+				&& name.starts_with(before)
+				&& name.ends_with(after)
+		},
+		None => pattern == name,
+	}
+}
+
+/// Checks that `short` (e.g. `origin/main`, without `refs/remotes/`) starts with the name of a
+/// configured remote, and that that remote's fetch refspecs would actually write to it.
+///
+/// Returns the name of the remote.
+pub fn validate_remote_branch(repo: &Repository, short: &BStr) -> miette::Result<BString>
+{
+	let remote_names: Vec<BString> = repo
+		.remote_names()
+		.into_iter()
+		.map(|name| name.into_owned())
+		.collect();
+
+	// Remote names may themselves contain slashes, so take the longest one that fits.
+	let remote_name = remote_names
+		.iter()
+		.filter(|name| {
+			short.len() > name.len()
+				&& short.starts_with(name.as_bytes())
+				&& short[name.len()] == b'/'
+		})
+		.max_by_key(|name| name.len());
+
+	let Some(remote_name) = remote_name else {
+		let first_component = short.split_str("/").next().unwrap_or_default();
+		let help = match suggest::close_matches(first_component, remote_names.iter()).first() {
+			Some(closest) => format!("did you mean '{}'?", closest),
+			None if remote_names.is_empty() => String::from("this repository has no remotes configured"),
+			None => format!("configured remotes are: {}", bstr::join(", ", &remote_names).as_bstr()),
+		};
+
+		return Err(miette::miette!(
+			help = help,
+			"'{}' does not start with the name of a configured remote",
+			short,
+		));
+	};
+
+	let remote = repo
+		.find_remote(remote_name.as_bstr())
+		.map_err(|e| miette::miette!(
+			help = format!("set remote.{}.url, or use `git remote add`", remote_name),
+			"remote '{}' is not usable: {}",
+			remote_name,
+			e,
+		))?;
+
+	if remote.url(Direction::Fetch).is_none() {
+		return Err(miette::miette!(
+			help = format!("set remote.{}.url, or use `git remote add`", remote_name),
+			"remote '{}' has no url configured",
+			remote_name,
+		));
+	}
+
+	let full_name: BString = [b"refs/remotes/".as_slice(), short.as_bytes()].concat().into();
+
+	let destinations: Vec<BString> = remote
+		.refspecs(Direction::Fetch)
+		.iter()
+		.filter_map(|spec| spec.to_ref().destination().map(ToOwned::to_owned))
+		.collect();
+
+	debug!("fetch destinations for remote {}: {:?}", remote_name, destinations);
+
+	if !destinations.iter().any(|dst| matches_pattern(dst.as_bstr(), full_name.as_bstr())) {
+		let mapped = if destinations.is_empty() {
+			String::from("nothing")
+		} else {
+			bstr::join(", ", &destinations).to_str_lossy().into_owned()
+		};
+
+		return Err(miette::miette!(
+			help = format!(
+				"remote.{}.fetch maps to {}; `git fetch` would never update or prune this ref",
+				remote_name,
+				mapped,
+			),
+			"{} is not a ref fetches from '{}' are stored in",
+			full_name,
+			remote_name,
+		));
+	}
+
+	Ok(remote_name.clone())
+}
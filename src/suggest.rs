@@ -0,0 +1,133 @@
+//! "Did you mean" helpers for names the user probably typo'd.
+
+use bstr::{BStr, ByteSlice};
+use gix::refs::FullName;
+use gix::Repository;
+use tap::TapFallible;
+
+#[allow(unused)]
+use log::{trace, debug, warn, info, error};
+
+/// The most refs we suggest for a name that doesn't exist.
+const MAX_REF_SUGGESTIONS: usize = 5;
+
+/// Edit distance between two byte strings, where swapping two adjacent bytes counts as
+/// one edit, since that's such a common typo (optimal string alignment distance).
+pub fn edit_distance(a: &[u8], b: &[u8]) -> usize
+{
+	// Dynamic programming table, keeping only the rows we still need.
+	let mut prev_prev: Vec<usize> = vec![0; b.len() + 1];
+	let mut prev: Vec<usize> = (0..=b.len()).collect();
+	let mut cur: Vec<usize> = vec![0; b.len() + 1];
+
+	for (i, &a_byte) in a.iter().enumerate() {
+		cur[0] = i + 1;
+		for (j, &b_byte) in b.iter().enumerate() {
+			let substitution = prev[j] + usize::from(a_byte != b_byte);
+			cur[j + 1] = substitution.min(prev[j + 1] + 1).min(cur[j] + 1);
+
+			if i > 0 && j > 0 && a_byte == b[j - 1] && a[i - 1] == b_byte {
+				cur[j + 1] = cur[j + 1].min(prev_prev[j - 1] + 1);
+			}
+		}
+		std::mem::swap(&mut prev_prev, &mut prev);
+		std::mem::swap(&mut prev, &mut cur);
+	}
+
+	prev[b.len()]
+}
+
+/// Candidates close enough to `target` to plausibly be a typo of it, closest first.
+pub fn close_matches<T, I>(target: &[u8], candidates: I) -> Vec<T>
+where
+	T: AsRef<[u8]>,
+	I: IntoIterator<Item = T>,
+{
+	close_matches_by_key(target, candidates, |candidate| candidate.as_ref().to_vec())
+}
+
+/// Like [close_matches], but comparing `target` to what `key` returns for each candidate.
+pub fn close_matches_by_key<T, I, K, F>(target: &[u8], candidates: I, key: F) -> Vec<T>
+where
+	I: IntoIterator<Item = T>,
+	K: AsRef<[u8]>,
+	F: Fn(&T) -> K,
+{
+	let max_distance = (target.len() / 3).max(1);
+
+	let mut matches: Vec<(usize, T)> = candidates
+		.into_iter()
+		.map(|candidate| (edit_distance(target, key(&candidate).as_ref()), candidate))
+		.filter(|(distance, _candidate)| *distance <= max_distance)
+		.collect();
+
+	// Stable, so equally close candidates stay in the order we were given them.
+	matches.sort_by_key(|(distance, _candidate)| *distance);
+
+	matches
+		.into_iter()
+		.map(|(_distance, candidate)| candidate)
+		.collect()
+}
+
+/// Existing refs whose names are close to `name`, closest first.
+///
+/// Names are compared shortened, like `topic` for `refs/heads/topic`, so a typo in a short
+/// name counts for as much whether or not it was fully qualified.
+pub fn similar_refs(repo: &Repository, name: &BStr) -> Vec<FullName>
+{
+	let all_refs = repo
+		.references()
+		.tap_err(|e| warn!("ignoring error listing refs to suggest: {}", e));
+	let Ok(all_refs) = all_refs else {
+		return Vec::new();
+	};
+	let Ok(iter) = all_refs.all().tap_err(|e| warn!("ignoring error listing refs to suggest: {}", e)) else {
+		return Vec::new();
+	};
+
+	let names: Vec<FullName> = iter
+		.filter_map(|r| r.tap_err(|e| warn!("ignoring error listing refs to suggest: {}", e)).ok())
+		.map(|r| r.name().to_owned())
+		.collect();
+
+	let short = match FullName::try_from(name) {
+		Ok(full) => full.as_ref().shorten().to_owned(),
+		Err(_e) => name.to_owned(),
+	};
+	let mut matches = close_matches_by_key(short.as_bytes(), names, |full| full.as_ref().shorten().to_owned());
+	matches.truncate(MAX_REF_SUGGESTIONS);
+
+	matches
+}
+
+/// An error for the ref `name` not existing, which suggests similar ones that do, or `--new`
+/// if `can_create` and there are none.
+pub fn missing_ref(repo: &Repository, name: &BStr, can_create: bool) -> miette::Report
+{
+	let similar = similar_refs(repo, name);
+
+	let describe = |full: &FullName| {
+		let shown = if name.starts_with(b"refs/") {
+			full.as_bstr()
+		} else {
+			full.as_ref().shorten()
+		};
+		format!("'{}' ({})", shown, crate::category_name(full.as_ref()))
+	};
+
+	let help = match similar.as_slice() {
+		[] if can_create => Some(String::from("pass --new to create it")),
+		[] => None,
+		[only] => Some(format!("did you mean {}?", describe(only))),
+		many => Some(format!(
+			"did you mean one of these?\n{}",
+			many.iter().map(|full| format!("  {}", describe(full))).collect::<Vec<_>>().join("\n"),
+		)),
+	};
+
+	match help {
+		Some(help) => miette::miette!(help = help, "ref '{}' does not exist", name),
+		None => miette::miette!("ref '{}' does not exist", name),
+	}
+}
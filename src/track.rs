@@ -0,0 +1,220 @@
+//! Upstream tracking configuration (`branch.<name>.remote` and `branch.<name>.merge`)
+//! for newly created branches.
+
+use std::path::PathBuf;
+
+use bstr::{BStr, BString, ByteSlice};
+use gix::refs::{Category as RefCategory, FullNameRef};
+use gix::remote::Direction;
+use gix::Repository;
+use miette::{Context, IntoDiagnostic};
+
+#[allow(unused)]
+use log::{trace, debug, warn, info, error};
+
+/// What `branch.autoSetupMerge` says to do, see git-config(1).
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum AutoSetupMerge
+{
+	/// `false`: never set up tracking.
+	Never,
+
+	/// `true` (the default): track when starting from a remote-tracking branch.
+	Remote,
+
+	/// `always`: track when starting from a remote-tracking or a local branch.
+	Always,
+
+	/// `inherit`: copy the starting branch's own upstream.
+	Inherit,
+
+	/// `simple`: track when starting from a remote-tracking branch of the same name.
+	Simple,
+}
+
+impl AutoSetupMerge
+{
+	pub fn from_config(repo: &Repository) -> Self
+	{
+		let Some(value) = repo.config_snapshot().string("branch.autoSetupMerge") else {
+			return Self::Remote;
+		};
+
+		match value.to_ascii_lowercase().as_bstr().to_str_lossy().as_ref() {
+			"always" => Self::Always,
+			"inherit" => Self::Inherit,
+			"simple" => Self::Simple,
+			"false" | "no" | "off" | "0" => Self::Never,
+			_ => Self::Remote,
+		}
+	}
+}
+
+/// The upstream of a branch, as stored in its config.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Upstream
+{
+	/// `branch.<name>.remote`; `.` for local branches.
+	pub remote: BString,
+
+	/// `branch.<name>.merge`, the full name of the ref *on the remote*.
+	pub merge: BString,
+}
+
+impl Upstream
+{
+	/// How git shows this upstream, e.g. `origin/main`.
+	pub fn display_name(&self) -> BString
+	{
+		let short_merge = self.merge.strip_prefix(b"refs/heads/").unwrap_or(&self.merge);
+		if self.remote == "." {
+			short_merge.into()
+		} else {
+			[self.remote.as_bytes(), b"/", short_merge].concat().into()
+		}
+	}
+}
+
+/// Finds the remote whose fetch refspecs map to `tracking_ref`, and the name of the
+/// ref on that remote they map it from.
+fn remote_source_of(repo: &Repository, tracking_ref: &BStr) -> Option<Upstream>
+{
+	for remote_name in repo.remote_names() {
+		let remote = match repo.find_remote(remote_name.as_ref()) {
+			Ok(remote) => remote,
+			Err(e) => {
+				warn!("ignoring error accessing remote {}: {}", remote_name, e);
+				continue;
+			},
+		};
+
+		for spec in remote.refspecs(Direction::Fetch) {
+			let spec = spec.to_ref();
+			let (Some(src), Some(dst)) = (spec.source(), spec.destination()) else {
+				continue;
+			};
+
+			let merge: Option<BString> = match (src.split_once_str("*"), dst.split_once_str("*")) {
+				(Some((src_before, src_after)), Some((dst_before, dst_after))) => {
+					tracking_ref
+						.strip_prefix(dst_before)
+						.and_then(|rest| rest.strip_suffix(dst_after))
+						.map(|matched| [src_before, matched, src_after].concat().into())
+				},
+				(None, None) if dst == tracking_ref => Some(src.to_owned()),
+				_ => None,
+			};
+
+			if let Some(merge) = merge {
+				return Some(Upstream { remote: remote_name.into_owned(), merge });
+			}
+		}
+	}
+
+	None
+}
+
+/// Decides what upstream, if any, a new branch `new_short` started from `start` should track.
+///
+/// `forced` is true for an explicit `--track`, which tracks any branch we can.
+pub fn upstream_for(
+	repo: &Repository,
+	new_short: &BStr,
+	start: &FullNameRef,
+	mode: AutoSetupMerge,
+	forced: bool,
+) -> Option<Upstream>
+{
+	let start_name = start.as_bstr();
+	let mode = if forced { AutoSetupMerge::Always } else { mode };
+
+	match start.category() {
+		Some(RefCategory::RemoteBranch) => {
+			let upstream = match mode {
+				AutoSetupMerge::Never | AutoSetupMerge::Inherit => return None,
+				AutoSetupMerge::Remote | AutoSetupMerge::Always | AutoSetupMerge::Simple => {
+					remote_source_of(repo, start_name)?
+				},
+			};
+
+			if mode == AutoSetupMerge::Simple && upstream.merge.strip_prefix(b"refs/heads/") != Some(new_short.as_bytes()) {
+				debug!("not tracking {} for {} due to branch.autoSetupMerge=simple", start_name, new_short);
+				return None;
+			}
+
+			Some(upstream)
+		},
+		Some(RefCategory::LocalBranch) => match mode {
+			AutoSetupMerge::Always => Some(Upstream {
+				remote: BString::from("."),
+				merge: start_name.to_owned(),
+			}),
+			AutoSetupMerge::Inherit => {
+				let short = start.shorten();
+				let config = repo.config_snapshot();
+				let remote = config.string(format!("branch.{}.remote", short).as_str())?;
+				let merge = config.string(format!("branch.{}.merge", short).as_str())?;
+				Some(Upstream { remote: remote.into_owned(), merge: merge.into_owned() })
+			},
+			_ => None,
+		},
+		_ => None,
+	}
+}
+
+/// The repository's config file with a new branch's upstream written to its lock file,
+/// waiting for the branch to be created.
+pub struct PendingUpstream
+{
+	lock: gix::lock::File,
+	config_path: PathBuf,
+}
+
+/// Writes `upstream` as the upstream of branch `short` to a locked copy of the repository's own
+/// config file, so anything in the way shows up before the branch is created.
+///
+/// The config itself doesn't change until [`PendingUpstream::commit()`]; dropping it leaves
+/// the config as it was.
+pub fn prepare_upstream(repo: &Repository, short: &BStr, upstream: &Upstream) -> miette::Result<PendingUpstream>
+{
+	let config_path = repo.common_dir().join("config");
+
+	// Lock first, so nobody can change the config between us reading and replacing it.
+	let mut lock = gix::lock::File::acquire_to_update_resource(&config_path, gix::lock::acquire::Fail::Immediately, None)
+		.into_diagnostic()
+		.with_context(|| format!("while locking {}", config_path.display()))?;
+
+	let mut config = gix::config::File::from_path_no_includes(config_path.clone(), gix::config::Source::Local)
+		.into_diagnostic()
+		.with_context(|| format!("while reading {}", config_path.display()))?;
+
+	config
+		.set_raw_value_by("branch", Some(short), "remote", upstream.remote.as_bstr())
+		.into_diagnostic()?;
+	config
+		.set_raw_value_by("branch", Some(short), "merge", upstream.merge.as_bstr())
+		.into_diagnostic()?;
+
+	config
+		.write_to(&mut lock)
+		.into_diagnostic()
+		.with_context(|| format!("while writing {}", config_path.display()))?;
+
+	Ok(PendingUpstream { lock, config_path })
+}
+
+impl PendingUpstream
+{
+	/// Replaces the config file with the one written by [`prepare_upstream()`].
+	pub fn commit(self) -> miette::Result<()>
+	{
+		let config_path = self.config_path;
+		self.lock
+			.commit()
+			.map_err(|e| e.error)
+			.into_diagnostic()
+			.with_context(|| format!("while writing {}", config_path.display()))?;
+
+		Ok(())
+	}
+}
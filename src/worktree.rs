@@ -0,0 +1,90 @@
+//! Per-worktree refs, like `HEAD` and `refs/bisect/*`, and git's `main-worktree/` and
+//! `worktrees/<id>/` syntax for naming another worktree's copy of them.
+
+use std::path::Path;
+
+use bstr::{BStr, BString, ByteSlice};
+use gix::Repository;
+use tap::TapFallible;
+
+#[allow(unused)]
+use log::{trace, debug, warn, info, error};
+
+/// Which worktree a ref name refers to.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Owner<'a>
+{
+	/// The one we're running in, for unqualified names.
+	Current,
+
+	/// `main-worktree/...`
+	Main,
+
+	/// `worktrees/<id>/...`
+	Linked(&'a BStr),
+}
+
+/// Splits a `main-worktree/` or `worktrees/<id>/` prefix off of `name`, returning the worktree
+/// it names and the name of the ref as seen from inside that worktree.
+pub fn split(name: &BStr) -> (Owner<'_>, &BStr)
+{
+	if let Some(inner) = name.strip_prefix(b"main-worktree/") {
+		return (Owner::Main, inner.as_bstr());
+	}
+
+	if let Some((id, inner)) = name.strip_prefix(b"worktrees/").and_then(|rest| rest.split_once_str("/")) {
+		return (Owner::Linked(id.as_bstr()), inner.as_bstr());
+	}
+
+	(Owner::Current, name)
+}
+
+/// Whether each worktree has its own ref named `name`, like git's `is_per_worktree_ref()`.
+///
+/// This includes pseudorefs like `HEAD`, which live outside of `refs/`.
+pub fn is_private(name: &BStr) -> bool
+{
+	!name.starts_with(b"refs/")
+		|| name.starts_with(b"refs/worktree/")
+		|| name.starts_with(b"refs/bisect/")
+		|| name.starts_with(b"refs/rewritten/")
+}
+
+/// Whether `tree_repo` is the same worktree as `repo`.
+pub fn is_same(repo: &Repository, tree_repo: &Repository) -> bool
+{
+	let canonical = |path: &Path| {
+		gix::path::realpath(path)
+			.tap_err(|e| warn!("ignoring error canonicalizing {}: {}", path.display(), e))
+			.ok()
+	};
+
+	match (canonical(repo.git_dir()), canonical(tree_repo.git_dir())) {
+		(Some(ours), Some(theirs)) => ours == theirs,
+		_ => false,
+	}
+}
+
+/// Whether `tree_repo` is the worktree `owner` refers to, from `repo`.
+pub fn is_owner(repo: &Repository, tree_repo: &Repository, owner: Owner) -> bool
+{
+	match owner {
+		Owner::Current => is_same(repo, tree_repo),
+		Owner::Main => tree_repo.worktree().is_some_and(|worktree| worktree.is_main()),
+		Owner::Linked(id) => tree_repo.worktree().is_some_and(|worktree| worktree.id() == Some(id)),
+	}
+}
+
+/// The name which refers to the per-worktree ref `name` of `repo`'s worktree from any
+/// worktree, e.g. `worktrees/<id>/refs/bisect/bad`. Shared refs are returned as-is.
+pub fn qualify(repo: &Repository, name: &BStr) -> BString
+{
+	if !is_private(name) || split(name).0 != Owner::Current {
+		return name.to_owned();
+	}
+
+	match repo.worktree().and_then(|worktree| worktree.id().map(ToOwned::to_owned)) {
+		Some(id) => [b"worktrees/", id.as_bytes(), b"/", name.as_bytes()].concat().into(),
+		None => [b"main-worktree/".as_slice(), name.as_bytes()].concat().into(),
+	}
+}
@@ -1,5 +1,6 @@
 use std::env;
 use std::path::{Path, PathBuf};
+use std::process::Stdio;
 use std::sync::LazyLock;
 
 use common::CommandWrapper;
@@ -49,6 +50,75 @@ fn setup_git(gitcmd: &dyn Fn() -> CommandWrapper)
 		.assert_spawn_exit_ok();
 }
 
+/// A fresh repository set up by [`setup_git`], in a temporary directory removed on drop.
+struct TestRepo
+{
+	tempdir: tempfile::TempDir,
+	dir: PathBuf,
+}
+
+impl TestRepo
+{
+	fn new() -> Self
+	{
+		Self::in_subdir(".")
+	}
+
+	/// Puts the repository in `subdir` of the temporary directory, leaving room for others.
+	fn in_subdir(subdir: &str) -> Self
+	{
+		let tempdir = tempfile::Builder::new()
+			.tempdir_in(CARGO_TARGET_TMPDIR)
+			.unwrap_or_else(|e| panic!("cannot create temporary directory in {} for test: {e}", CARGO_TARGET_TMPDIR));
+		let dir = tempdir.path().join(subdir);
+
+		std::fs::create_dir_all(&dir)
+			.unwrap_or_else(|e| panic!("cannot create {}: {e}", dir.display()));
+
+		let repo = Self { tempdir, dir };
+		setup_git(&|| repo.git());
+		repo
+	}
+
+	/// The temporary directory, for anything else a test needs alongside the repository.
+	fn root(&self) -> &Path
+	{
+		self.tempdir.path()
+	}
+
+	fn dir(&self) -> &Path
+	{
+		&self.dir
+	}
+
+	fn git(&self) -> CommandWrapper
+	{
+		CommandWrapper::new("git", GIT.as_path()).current_dir(&self.dir)
+	}
+
+	/// git-point in the repository, with stdin closed so that it can never prompt.
+	fn git_point(&self) -> CommandWrapper
+	{
+		CommandWrapper::cargo_bin("git-point")
+			.current_dir(&self.dir)
+			.stdin(Stdio::null())
+	}
+
+	fn commit(&self, message: &str)
+	{
+		self.git()
+			.args(["commit", "--allow-empty", "-m", message])
+			.assert_spawn_exit_ok();
+	}
+
+	fn branch(&self, name: &str)
+	{
+		self.git()
+			.args(["branch", name])
+			.assert_spawn_exit_ok();
+	}
+}
+
 #[test]
 fn basic()
 {
@@ -102,3 +172,1068 @@ fn basic()
 		assert_eq!(new_initial_branch_rev, second_commit);
 	});
 }
+
+#[test]
+fn new_invalid_name()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+
+	let output = repo.git_point()
+		.args(["--new=branch", "foo..bar", "@"])
+		.assert_spawn_exit_failure_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+
+	assert!(!stderr.contains("panicked"), "git-point panicked: {stderr}");
+	assert!(stderr.contains("is not a valid ref name"), "unexpected error: {stderr}");
+	assert!(stderr.contains("did you mean 'foo.bar'?"), "no suggestion given: {stderr}");
+}
+
+#[test]
+fn new_inferred_kind()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+
+	for (from, full_name) in [("refs/tags/v1.0", "refs/tags/v1.0"), ("heads/topic", "refs/heads/topic")] {
+		let output = repo.git_point()
+			.args(["--new", from, "@"])
+			.assert_spawn_exit_ok_with_output();
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		assert!(stderr.contains("Inferred"), "kind not inferred: {stderr}");
+
+		repo.git()
+			.args(["show-ref", "--verify", full_name])
+			.assert_spawn_exit_ok();
+	}
+
+	// The short form, and an explicit kind after the names, parse too.
+	repo.git_point()
+		.args(["-n", "refs/tags/v1.1", "@"])
+		.assert_spawn_exit_ok();
+	repo.git_point()
+		.args(["topic3", "@", "-n=branch"])
+		.assert_spawn_exit_ok();
+	repo.git()
+		.args(["show-ref", "--verify", "refs/tags/v1.1", "refs/heads/topic3"])
+		.assert_spawn_exit_ok();
+
+	let output = repo.git_point()
+		.args(["--new", "topic2", "@"])
+		.assert_spawn_exit_failure_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("cannot infer what kind of ref 'topic2' should be"), "unexpected error: {stderr}");
+
+	// An explicit kind with the kind's prefix would make `refs/tags/tags/v2.0`.
+	let output = repo.git_point()
+		.args(["--new=tag", "tags/v2.0", "@"])
+		.assert_spawn_exit_failure_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("refusing to create tag 'tags/v2.0' as refs/tags/tags/v2.0"), "unexpected error: {stderr}");
+	assert!(stderr.contains("pass --new without a <KIND>"), "no help given: {stderr}");
+
+	repo.git()
+		.args(["show-ref", "--verify", "refs/tags/tags/v2.0"])
+		.assert_spawn_exit_failure_with_output();
+}
+
+#[test]
+fn new_conflicting()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.branch("topic");
+
+	for name in ["topic/sub", "topic/sub/deeper"] {
+		let output = repo.git_point()
+			.args(["--new=branch", name, "@"])
+			.assert_spawn_exit_failure_with_output();
+		let stderr = String::from_utf8_lossy(&output.stderr);
+
+		assert_eq!(output.status.code(), Some(2));
+		assert!(stderr.contains("conflicts with existing ref(s): refs/heads/topic"), "unexpected error: {stderr}");
+		assert!(!stderr.contains("WARN"), "unexpected warning: {stderr}");
+	}
+}
+
+#[test]
+fn new_ambiguous()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.git()
+		.args(["tag", "v1.0"])
+		.assert_spawn_exit_ok();
+
+	// A branch named like an existing tag would make `v1.0` ambiguous from then on.
+	let output = repo.git_point()
+		.args(["--new=branch", "v1.0", "@"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(3));
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("short name 'v1.0' is already used by: refs/tags/v1.0"), "unexpected output: {stderr}");
+
+	let output = repo.git()
+		.args(["show-ref", "--verify", "--quiet", "refs/heads/v1.0"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(1));
+
+	repo.git_point()
+		.args(["--new=branch", "v1.0", "@", "--allow-ambiguous"])
+		.assert_spawn_exit_ok();
+	repo.git()
+		.args(["show-ref", "--verify", "--quiet", "refs/heads/v1.0"])
+		.assert_spawn_exit_ok();
+}
+
+#[test]
+fn new_remote_branch()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+
+	// Only fetches main into refs/remotes/origin/main.
+	repo.git()
+		.args(["remote", "add", "--track", "main", "origin", "."])
+		.assert_spawn_exit_ok();
+	repo.git()
+		.args(["remote", "add", "upstream", "."])
+		.assert_spawn_exit_ok();
+
+	let output = repo.git_point()
+		.args(["--new=remote-branch", "orign/main", "@"])
+		.assert_spawn_exit_failure_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("does not start with the name of a configured remote"), "unexpected error: {stderr}");
+	assert!(stderr.contains("did you mean 'origin'?"), "no suggestion given: {stderr}");
+
+	let output = repo.git_point()
+		.args(["--new=remote-branch", "origin/topic", "@"])
+		.assert_spawn_exit_failure_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("refs/remotes/origin/topic is not a ref fetches from 'origin'"), "unexpected error: {stderr}");
+	assert!(stderr.contains("refs/remotes/origin/main"), "fetch mapping not shown: {stderr}");
+
+	repo.git_point()
+		.args(["--new=remote-branch", "--remote-head", "upstream/main", "@"])
+		.assert_spawn_exit_ok();
+
+	let output = repo.git()
+		.args(["symbolic-ref", "refs/remotes/upstream/HEAD"])
+		.assert_spawn_exit_ok_with_output();
+	assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "refs/remotes/upstream/main");
+
+	// There's already a HEAD for upstream now.
+	let output = repo.git_point()
+		.args(["--new=remote-branch", "--remote-head", "upstream/topic", "@"])
+		.assert_spawn_exit_failure_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert_eq!(output.status.code(), Some(2));
+	assert!(stderr.contains("refusing to create ref refs/remotes/upstream/HEAD"), "unexpected error: {stderr}");
+
+	repo.git()
+		.args(["show-ref", "--verify", "refs/remotes/upstream/topic"])
+		.assert_spawn_exit_failure_with_output();
+}
+
+#[test]
+fn new_branch_tracking()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.branch("feature");
+
+	repo.git()
+		.args(["remote", "add", "origin", "."])
+		.assert_spawn_exit_ok();
+	repo.git()
+		.args(["fetch", "--quiet", "origin"])
+		.assert_spawn_exit_ok();
+	repo.git()
+		.args(["branch", "--delete", "feature"])
+		.assert_spawn_exit_ok();
+
+	let upstream_of = |branch: &str| {
+		let output = repo.git()
+			.args(["for-each-ref", "--format=%(upstream:short)"])
+			.arg(format!("refs/heads/{branch}"))
+			.assert_spawn_exit_ok_with_output();
+		String::from_utf8_lossy(&output.stdout).trim().to_owned()
+	};
+	let set_auto_setup_merge = |mode: &str| {
+		repo.git()
+			.args(["config", "branch.autoSetupMerge", mode])
+			.assert_spawn_exit_ok();
+	};
+
+	// By default, only remote-tracking branches are tracked.
+	repo.git_point()
+		.args(["--new=branch", "from-remote", "origin/main"])
+		.assert_spawn_exit_ok();
+	assert_eq!(upstream_of("from-remote"), "origin/main");
+
+	repo.git_point()
+		.args(["--new=branch", "--no-track", "untracked", "origin/main"])
+		.assert_spawn_exit_ok();
+	assert_eq!(upstream_of("untracked"), "");
+
+	repo.git_point()
+		.args(["--new=branch", "from-local", "main"])
+		.assert_spawn_exit_ok();
+	assert_eq!(upstream_of("from-local"), "");
+
+	repo.git_point()
+		.args(["--new=branch", "--track", "forced", "main"])
+		.assert_spawn_exit_ok();
+	assert_eq!(upstream_of("forced"), "main");
+
+	// `simple` only tracks a remote-tracking branch of the same name.
+	set_auto_setup_merge("simple");
+	repo.git_point()
+		.args(["--new=branch", "renamed", "origin/feature"])
+		.assert_spawn_exit_ok();
+	assert_eq!(upstream_of("renamed"), "");
+
+	repo.git_point()
+		.args(["--new=branch", "feature", "origin/feature"])
+		.assert_spawn_exit_ok();
+	assert_eq!(upstream_of("feature"), "origin/feature");
+
+	// `inherit` copies the upstream of the branch started from.
+	set_auto_setup_merge("inherit");
+	repo.git_point()
+		.args(["--new=branch", "inherited", "from-remote"])
+		.assert_spawn_exit_ok();
+	assert_eq!(upstream_of("inherited"), "origin/main");
+
+	// If the config can't be written, the branch isn't created either.
+	set_auto_setup_merge("true");
+	let config_lock = repo.dir().join(".git/config.lock");
+	std::fs::write(&config_lock, "")
+		.unwrap_or_else(|e| panic!("cannot create {}: {e}", config_lock.display()));
+
+	let output = repo.git_point()
+		.args(["--new=branch", "blocked", "origin/main"])
+		.assert_spawn_exit_failure_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("while setting upstream of refs/heads/blocked"), "unexpected error: {stderr}");
+	assert!(!stderr.contains("Created"), "branch created anyway: {stderr}");
+
+	repo.git()
+		.args(["show-ref", "--verify", "refs/heads/blocked"])
+		.assert_spawn_exit_failure_with_output();
+}
+
+#[test]
+fn bare()
+{
+	let repo = TestRepo::in_subdir("source");
+	let bare_dir = repo.root().join("bare.git");
+	let worktree_dir = repo.root().join("worktree");
+
+	let baregitcmd = || CommandWrapper::new("git", GIT.as_path()).current_dir(&bare_dir);
+	let gitpointcmd = || CommandWrapper::cargo_bin("git-point").current_dir(&bare_dir);
+
+	repo.commit("initial commit");
+	repo.branch("initial");
+	repo.commit("second-commit");
+
+	repo.git()
+		.arg("clone")
+		.arg("--bare")
+		.arg(repo.dir())
+		.arg(&bare_dir)
+		.assert_spawn_exit_ok();
+
+	let rev_parse = |rev: &str| {
+		let output = baregitcmd()
+			.args(["rev-parse", rev])
+			.assert_spawn_exit_ok_with_output();
+		String::from_utf8(output.stdout).expect("rev-parse output is not UTF-8")
+	};
+
+	let initial_commit = rev_parse("initial");
+	let second_commit = rev_parse("main");
+
+	// Nothing is checked out in a bare repo, so moving any ref, including the one HEAD
+	// points to, is fine.
+	gitpointcmd()
+		.args(["initial", "HEAD"])
+		.assert_spawn_exit_ok();
+	assert_eq!(rev_parse("initial"), second_commit);
+
+	gitpointcmd()
+		.args(["main", initial_commit.trim()])
+		.assert_spawn_exit_ok();
+	assert_eq!(rev_parse("main"), initial_commit);
+
+	// But linked worktrees of a bare repo still count.
+	baregitcmd()
+		.args(["worktree", "add"])
+		.arg(&worktree_dir)
+		.arg("initial")
+		.assert_spawn_exit_ok();
+
+	let output = gitpointcmd()
+		.args(["initial", "main"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(1));
+	assert_eq!(rev_parse("initial"), second_commit);
+}
+
+#[test]
+fn repository_location()
+{
+	let repo = TestRepo::in_subdir("repo");
+	let root = repo.root();
+
+	repo.commit("initial commit");
+	repo.branch("topic");
+	repo.commit("second commit");
+
+	let output = repo.git()
+		.args(["rev-parse", "main", "main~"])
+		.assert_spawn_exit_ok_with_output();
+	let stdout = String::from_utf8_lossy(&output.stdout);
+	let ids: Vec<&str> = stdout.lines().collect();
+
+	// Each of these moves topic back and forth between main and main~, so none is a no-op.
+	let mut moves = 0;
+	let mut assert_moved = |git_point: CommandWrapper| {
+		moves += 1;
+		let to = if moves % 2 == 1 { "main" } else { "main~" };
+		git_point
+			.args(["topic", to])
+			.assert_spawn_exit_ok();
+
+		let output = repo.git()
+			.args(["rev-parse", "topic"])
+			.assert_spawn_exit_ok_with_output();
+		assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), ids[(moves + 1) % 2], "after move {moves}");
+	};
+
+	// From a subdirectory of the worktree.
+	let subdir = repo.dir().join("sub/dir");
+	std::fs::create_dir_all(&subdir)
+		.unwrap_or_else(|e| panic!("cannot create {}: {e}", subdir.display()));
+	assert_moved(repo.git_point().current_dir(&subdir));
+
+	// -C, including repeated ones relative to the previous.
+	assert_moved(repo.git_point().current_dir(root).args(["-C", "repo"]));
+	assert_moved(repo.git_point().current_dir("/").arg("-C").arg(root).args(["-C", "repo/sub"]));
+
+	// --git-dir and --work-tree, relative to -C.
+	assert_moved(repo.git_point().current_dir(root).args(["-C", "repo", "--git-dir", ".git", "--work-tree", "."]));
+	assert_moved(
+		repo.git_point()
+			.current_dir(root)
+			.arg("--git-dir")
+			.arg(repo.dir().join(".git"))
+			.arg("--work-tree")
+			.arg(repo.dir()),
+	);
+
+	// And the environment variables git uses for them.
+	assert_moved(
+		repo.git_point()
+			.current_dir(root)
+			.env("GIT_DIR", repo.dir().join(".git"))
+			.env("GIT_WORK_TREE", repo.dir()),
+	);
+}
+
+#[test]
+fn update_worktree()
+{
+	let repo = TestRepo::new();
+	let dir = repo.dir();
+
+	let write = |name: &str, contents: &str| {
+		std::fs::write(dir.join(name), contents)
+			.unwrap_or_else(|e| panic!("cannot write {name}: {e}"));
+	};
+	let read = |name: &str| {
+		std::fs::read_to_string(dir.join(name))
+			.unwrap_or_else(|e| panic!("cannot read {name}: {e}"))
+	};
+
+	write("changed", "one\n");
+	write("unchanged", "same\n");
+	repo.git()
+		.args(["add", "changed", "unchanged"])
+		.assert_spawn_exit_ok();
+	repo.git()
+		.args(["commit", "-m", "one"])
+		.assert_spawn_exit_ok();
+	repo.branch("one");
+
+	write("changed", "two\n");
+	write("added", "new\n");
+	repo.git()
+		.args(["add", "changed", "added"])
+		.assert_spawn_exit_ok();
+	repo.git()
+		.args(["commit", "-m", "two"])
+		.assert_spawn_exit_ok();
+	repo.branch("two");
+
+	// Local changes to a file that doesn't differ between the commits survive --update-worktree=keep...
+	write("unchanged", "local\n");
+	repo.git_point()
+		.args(["main", "one", "--update-worktree", "keep"])
+		.assert_spawn_exit_ok();
+	assert_eq!(read("changed"), "one\n");
+	assert_eq!(read("unchanged"), "local\n");
+	assert!(!dir.join("added").exists());
+
+	// ...but local changes to a file that does differ are refused.
+	write("changed", "local\n");
+	let output = repo.git_point()
+		.args(["main", "two", "--update-worktree", "keep"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(1));
+	assert_eq!(read("changed"), "local\n");
+
+	// A ref that doesn't move leaves its worktrees alone, even with --update-worktree=hard...
+	repo.git_point()
+		.args(["main", "one", "--update-worktree", "hard"])
+		.assert_spawn_exit_ok();
+	assert_eq!(read("changed"), "local\n");
+	assert_eq!(read("unchanged"), "local\n");
+
+	// ...but otherwise that discards them.
+	repo.git_point()
+		.args(["main", "two", "--update-worktree", "hard"])
+		.assert_spawn_exit_ok();
+	assert_eq!(read("changed"), "two\n");
+	assert_eq!(read("unchanged"), "same\n");
+	assert_eq!(read("added"), "new\n");
+
+	let output = repo.git()
+		.args(["status", "--porcelain"])
+		.assert_spawn_exit_ok_with_output();
+	assert_eq!(String::from_utf8_lossy(&output.stdout), "");
+}
+
+#[test]
+fn in_progress_rebase()
+{
+	let repo = TestRepo::new();
+	let dir = repo.dir();
+
+	let commit = |contents: &str| {
+		std::fs::write(dir.join("file"), contents)
+			.unwrap_or_else(|e| panic!("cannot write file: {e}"));
+		repo.git()
+			.args(["commit", "--all", "-m", contents])
+			.assert_spawn_exit_ok();
+	};
+
+	std::fs::write(dir.join("file"), "base")
+		.unwrap_or_else(|e| panic!("cannot write file: {e}"));
+	repo.git()
+		.args(["add", "file"])
+		.assert_spawn_exit_ok();
+	commit("base");
+
+	repo.git()
+		.args(["checkout", "-b", "topic"])
+		.assert_spawn_exit_ok();
+	commit("topic");
+
+	repo.git()
+		.args(["checkout", "main"])
+		.assert_spawn_exit_ok();
+	commit("main");
+
+	// This conflicts, leaving HEAD detached with topic recorded as the branch being rebased.
+	repo.git()
+		.args(["rebase", "main", "topic"])
+		.assert_spawn_exit_failure_with_output();
+
+	let output = repo.git_point()
+		.args(["topic", "main"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(1));
+	assert!(String::from_utf8_lossy(&output.stderr).contains("rebase"));
+
+	repo.git()
+		.args(["rebase", "--abort"])
+		.assert_spawn_exit_ok();
+	repo.git()
+		.args(["checkout", "main"])
+		.assert_spawn_exit_ok();
+
+	repo.git_point()
+		.args(["topic", "main"])
+		.assert_spawn_exit_ok();
+}
+
+#[test]
+fn per_worktree_refs()
+{
+	let repo = TestRepo::in_subdir("main");
+	let linked_dir = repo.root().join("linked");
+
+	let gitcmd = || repo.git();
+	let linkedgitcmd = || CommandWrapper::new("git", GIT.as_path()).current_dir(&linked_dir);
+
+	repo.commit("initial commit");
+	repo.commit("second commit");
+
+	gitcmd()
+		.args(["worktree", "add", "--detach"])
+		.arg(&linked_dir)
+		.arg("HEAD~")
+		.assert_spawn_exit_ok();
+
+	// Each worktree gets its own refs/worktree/mark.
+	gitcmd()
+		.args(["update-ref", "refs/worktree/mark", "HEAD"])
+		.assert_spawn_exit_ok();
+	linkedgitcmd()
+		.args(["update-ref", "refs/worktree/mark", "HEAD"])
+		.assert_spawn_exit_ok();
+
+	let rev_parse = |gitcmd: &dyn Fn() -> CommandWrapper, rev: &str| {
+		let output = gitcmd()
+			.args(["rev-parse", rev])
+			.assert_spawn_exit_ok_with_output();
+		String::from_utf8(output.stdout).expect("rev-parse output is not UTF-8")
+	};
+
+	let main_mark = rev_parse(&gitcmd, "refs/worktree/mark");
+	let main_head = rev_parse(&gitcmd, "HEAD");
+
+	repo.git_point()
+		.args(["worktrees/linked/refs/worktree/mark", "main"])
+		.assert_spawn_exit_ok();
+	assert_eq!(rev_parse(&linkedgitcmd, "refs/worktree/mark"), main_head);
+	assert_eq!(rev_parse(&gitcmd, "refs/worktree/mark"), main_mark);
+
+	// The linked worktree's detached HEAD is still checked out there.
+	let output = repo.git_point()
+		.args(["worktrees/linked/HEAD", "main"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn protected()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.branch("release");
+	repo.commit("second commit");
+	repo.git()
+		.args(["tag", "v1.0", "HEAD~"])
+		.assert_spawn_exit_ok();
+
+	for protection in ["create-only:refs/tags/*", "ff-only:refs/heads/release"] {
+		repo.git()
+			.args(["config", "--add", "point.protect", protection])
+			.assert_spawn_exit_ok();
+	}
+
+	// Tags can be created, but not moved.
+	repo.git_point()
+		.args(["v2.0", "main", "--new=tag"])
+		.assert_spawn_exit_ok();
+	// --retag gets past the refusal to move tags, so only the protection can refuse this.
+	let output = repo.git_point()
+		.args(["v1.0", "main", "--retag"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(4));
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("point.protect=create-only:refs/tags/*"), "unexpected output: {stderr}");
+
+	// release can move forward, but not back.
+	repo.git_point()
+		.args(["release", "main"])
+		.assert_spawn_exit_ok();
+	let output = repo.git_point()
+		.args(["release", "v1.0"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(4));
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("point.protect=ff-only:refs/heads/release"), "unexpected output: {stderr}");
+
+	repo.git_point()
+		.args(["release", "v1.0", "--force-protected"])
+		.assert_spawn_exit_ok();
+}
+
+#[test]
+fn retag()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.git()
+		.args(["tag", "v1.0"])
+		.assert_spawn_exit_ok();
+	repo.commit("second commit");
+
+	let output = repo.git_point()
+		.args(["v1.0", "main"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(4));
+	assert!(String::from_utf8_lossy(&output.stderr).contains("+ "));
+
+	repo.git_point()
+		.args(["v1.0", "main", "--retag"])
+		.assert_spawn_exit_ok();
+
+	// An annotated tag stores a tag object, not the commit it's tagging.
+	repo.git()
+		.args(["tag", "--annotate", "-m", "version 0.1", "v0.1", "HEAD~"])
+		.assert_spawn_exit_ok();
+
+	let output = repo.git_point()
+		.args(["v0.1", "main", "--retag"])
+		.assert_spawn_exit_ok_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("v0.1 was an annotated tag; it's now a lightweight tag"), "unexpected output: {stderr}");
+
+	let output = repo.git()
+		.args(["cat-file", "-t", "v0.1"])
+		.assert_spawn_exit_ok_with_output();
+	assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "commit");
+}
+
+#[test]
+fn unrelated()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.branch("topic");
+	repo.git()
+		.args(["checkout", "--orphan", "other-project"])
+		.assert_spawn_exit_ok();
+	repo.commit("unrelated root commit");
+
+	let output = repo.git_point()
+		.args(["topic", "other-project"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(5));
+
+	repo.git_point()
+		.args(["topic", "other-project", "--allow-unrelated"])
+		.assert_spawn_exit_ok();
+}
+
+#[test]
+fn movement_summary()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.branch("topic");
+	repo.commit("second commit");
+
+	let output = repo.git_point()
+		.args(["topic", "main", "--log"])
+		.assert_spawn_exit_ok_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("[fast-forward, 1 ahead]"), "unexpected output: {stderr}");
+	assert!(stderr.contains("+ ") && stderr.contains("second commit"), "unexpected output: {stderr}");
+
+	let output = repo.git_point()
+		.args(["topic", "main~"])
+		.assert_spawn_exit_ok_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("[rewind, 1 behind]"), "unexpected output: {stderr}");
+}
+
+#[test]
+fn movement_summary_clock_skew()
+{
+	let repo = TestRepo::new();
+
+	let output = repo.git()
+		.arg("mktree")
+		.stdin(Stdio::null())
+		.assert_spawn_exit_ok_with_output();
+	let tree = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+	let commit_at = |time: u32, message: &str, parent: Option<&str>| -> String {
+		let date = format!("{time} +0000");
+		let mut git = repo.git()
+			.env("GIT_COMMITTER_DATE", &date)
+			.env("GIT_AUTHOR_DATE", &date)
+			.args(["commit-tree", &tree, "-m", message]);
+		if let Some(parent) = parent {
+			git = git.args(["-p", parent]);
+		}
+		let output = git.assert_spawn_exit_ok_with_output();
+		String::from_utf8_lossy(&output.stdout).trim().to_string()
+	};
+
+	// victim's commits are newer than the ones between it and the new tip, so walking back
+	// from both by date finds them long before finding that the new tip contains them too.
+	let base = commit_at(1_900_000_000, "base", None);
+	let old = commit_at(2_000_000_000, "old", Some(&base));
+	let mut new = old.clone();
+	for i in 1..=10 {
+		new = commit_at(1_000_000_000, &format!("skewed {i}"), Some(&new));
+	}
+	let new = commit_at(2_100_000_000, "new", Some(&new));
+
+	repo.git()
+		.args(["update-ref", "refs/heads/victim", &old])
+		.assert_spawn_exit_ok();
+	repo.git()
+		.args(["config", "point.protect", "ff-only:refs/heads/victim"])
+		.assert_spawn_exit_ok();
+
+	let output = repo.git_point()
+		.args(["victim", &new, "--log"])
+		.assert_spawn_exit_ok_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("[fast-forward, 11 ahead]"), "unexpected output: {stderr}");
+	assert!(!stderr.contains("- "), "unexpected output: {stderr}");
+}
+
+#[test]
+fn stat_and_range_diff()
+{
+	let repo = TestRepo::new();
+	let dir = repo.dir();
+
+	let commit = |name: &str, contents: &str| {
+		std::fs::write(dir.join(name), contents)
+			.unwrap_or_else(|e| panic!("cannot write {name}: {e}"));
+		repo.git()
+			.args(["add", name])
+			.assert_spawn_exit_ok();
+		repo.git()
+			.args(["commit", "-m", &format!("write {name}")])
+			.assert_spawn_exit_ok();
+	};
+
+	commit("base", "base\n");
+	repo.git()
+		.args(["checkout", "-b", "topic"])
+		.assert_spawn_exit_ok();
+	commit("topic", "one\ntwo\n");
+
+	// A rewritten version of topic, on top of new work on main.
+	repo.git()
+		.args(["checkout", "main"])
+		.assert_spawn_exit_ok();
+	commit("main", "main\n");
+	repo.git()
+		.args(["checkout", "--detach"])
+		.assert_spawn_exit_ok();
+	repo.git()
+		.args(["cherry-pick", "topic"])
+		.assert_spawn_exit_ok();
+
+	let output = repo.git_point()
+		.args(["topic", "HEAD", "--stat", "--range-diff"])
+		.assert_spawn_exit_ok_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+
+	assert!(stderr.contains("main | 1 +"), "unexpected output: {stderr}");
+	assert!(stderr.contains("1 file changed, 1 insertion(+), 0 deletions(-)"), "unexpected output: {stderr}");
+	// The cherry-picked commit is the same change as the original.
+	assert!(stderr.contains(" = 2: "), "unexpected output: {stderr}");
+	assert!(stderr.contains("-: ------- > 1: "), "unexpected output: {stderr}");
+}
+
+#[test]
+fn graph()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("base");
+	repo.git()
+		.args(["checkout", "-b", "topic"])
+		.assert_spawn_exit_ok();
+	repo.commit("topic work");
+	repo.git()
+		.args(["checkout", "main"])
+		.assert_spawn_exit_ok();
+	repo.commit("main work");
+	repo.branch("other");
+
+	let output = repo.git_point()
+		.args(["topic", "other", "--graph"])
+		.assert_spawn_exit_ok_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+
+	assert!(stderr.contains("main work (main, other) <- topic will be here"), "unexpected output: {stderr}");
+	assert!(stderr.contains("topic work <- topic was here"), "unexpected output: {stderr}");
+	assert!(stderr.contains("|/"), "unexpected output: {stderr}");
+	assert!(stderr.contains("  * ") && stderr.contains(" base\n"), "unexpected output: {stderr}");
+}
+
+#[test]
+fn confirm()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.commit("second commit");
+	repo.branch("topic");
+	repo.git()
+		.args(["config", "point.confirm", "rewind:refs/heads/*"])
+		.assert_spawn_exit_ok();
+
+	// Rewinding needs confirmation, which can't be given.
+	let output = repo.git_point()
+		.args(["topic", "topic~"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(6));
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("point.confirm=rewind:refs/heads/*"), "unexpected output: {stderr}");
+
+	let topic = repo.git()
+		.args(["rev-parse", "topic"])
+		.assert_spawn_exit_ok_with_output();
+	let main = repo.git()
+		.args(["rev-parse", "main"])
+		.assert_spawn_exit_ok_with_output();
+	assert_eq!(topic.stdout, main.stdout, "topic was moved without confirmation");
+
+	// Unless it's given up front.
+	repo.git_point()
+		.args(["topic", "topic~", "--yes"])
+		.assert_spawn_exit_ok();
+
+	// Fast-forwards don't need it, unless asked for with --interactive.
+	let output = repo.git_point()
+		.args(["topic", "main", "--interactive"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(6));
+	repo.git_point()
+		.args(["topic", "main"])
+		.assert_spawn_exit_ok();
+}
+
+#[test]
+fn ambiguous()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.git()
+		.args(["tag", "v1.0"])
+		.assert_spawn_exit_ok();
+	repo.branch("v1.0");
+	repo.commit("second commit");
+
+	let output = repo.git_point()
+		.args(["v1.0", "main"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(3));
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("refs/heads/v1.0 (branch)"), "unexpected output: {stderr}");
+	assert!(stderr.contains("refs/tags/v1.0  (tag)"), "unexpected output: {stderr}");
+	assert!(stderr.contains("They all point to"), "unexpected output: {stderr}");
+	assert!(stderr.contains("git point refs/tags/v1.0 main"), "unexpected output: {stderr}");
+
+	repo.branch("other");
+	repo.git()
+		.args(["branch", "-f", "v1.0", "main"])
+		.assert_spawn_exit_ok();
+	let output = repo.git_point()
+		.args(["other", "v1.0~0"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(3));
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("initial commit") && stderr.contains("second commit"), "unexpected output: {stderr}");
+	assert!(stderr.contains("They point to different commits"), "unexpected output: {stderr}");
+	assert!(stderr.contains("git point other refs/heads/v1.0~0"), "unexpected output: {stderr}");
+
+	repo.git_point()
+		.args(["heads/v1.0", "main"])
+		.assert_spawn_exit_ok();
+}
+
+#[test]
+fn suggest_missing()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.branch("feature/frobnicate-widgets");
+
+	let output = repo.git_point()
+		.args(["feature/frobincate-widgets", "main"])
+		.assert_spawn_exit_failure_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("did you mean 'feature/frobnicate-widgets' (branch)?"), "unexpected output: {stderr}");
+
+	let output = repo.git_point()
+		.args(["feature/frobnicate-widgets", "mian~0"])
+		.assert_spawn_exit_failure_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("did you mean 'main' (branch)?"), "unexpected output: {stderr}");
+
+	// Nothing close, so it probably should be created.
+	let output = repo.git_point()
+		.args(["something-else-entirely", "main"])
+		.assert_spawn_exit_failure_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("pass --new to create it"), "unexpected output: {stderr}");
+}
+
+#[test]
+fn no_op()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.branch("topic");
+
+	let reflog = || {
+		repo.git()
+			.args(["reflog", "show", "topic"])
+			.assert_spawn_exit_ok_with_output()
+			.stdout
+	};
+	let before = reflog();
+
+	let output = repo.git_point()
+		.args(["topic", "main"])
+		.assert_spawn_exit_ok_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("refs/heads/topic is already at"), "unexpected output: {stderr}");
+
+	let output = repo.git_point()
+		.args(["topic", "main", "--exit-code"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(7));
+
+	assert_eq!(reflog(), before, "a no-op wrote to the reflog");
+}
+
+#[test]
+fn expect()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.branch("topic");
+	repo.commit("second commit");
+
+	// Someone else already moved topic.
+	let output = repo.git_point()
+		.args(["topic", "main", "--expect", "main"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(8));
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("has moved"), "unexpected output: {stderr}");
+	assert!(stderr.contains("(second commit)") && stderr.contains("(initial commit)"), "unexpected output: {stderr}");
+
+	// An ambiguous --expect is refused like an ambiguous <TO>.
+	repo.git()
+		.args(["tag", "old", "main~"])
+		.assert_spawn_exit_ok();
+	repo.branch("old");
+	let output = repo.git_point()
+		.args(["topic", "main", "--expect", "old"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(3));
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("refname 'old' in 'old' is ambiguous"), "unexpected output: {stderr}");
+
+	repo.git_point()
+		.args(["topic", "main", "--expect", "main~"])
+		.assert_spawn_exit_ok();
+
+	// An annotated tag is expected to still store the tag object that points there.
+	repo.git()
+		.args(["tag", "--annotate", "--message", "release", "release", "main~"])
+		.assert_spawn_exit_ok();
+	repo.git_point()
+		.args(["release", "main", "--retag", "--expect", "main~"])
+		.assert_spawn_exit_ok();
+}
+
+#[test]
+fn check()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.branch("topic");
+	repo.commit("second commit");
+
+	// Checked out refs can be checked.
+	repo.git_point()
+		.args(["--check", "main", "HEAD"])
+		.assert_spawn_exit_ok();
+
+	let output = repo.git_point()
+		.args(["--check", "topic", "main"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(8));
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("- ") && stderr.contains(" second commit"), "unexpected output: {stderr}");
+	assert!(stderr.contains("+ ") && stderr.contains(" initial commit"), "unexpected output: {stderr}");
+	assert!(stderr.contains("topic is 0 ahead and 1 behind main"), "unexpected output: {stderr}");
+
+	// Ambiguous names are still refused.
+	repo.git()
+		.args(["tag", "topic", "main"])
+		.assert_spawn_exit_ok();
+	let output = repo.git_point()
+		.args(["--check", "topic", "main"])
+		.assert_spawn_exit_failure_with_output();
+	assert_eq!(output.status.code(), Some(3));
+
+	// --check never creates anything, so it doesn't suggest it.
+	let output = repo.git_point()
+		.args(["--check", "nonexistent", "main"])
+		.assert_spawn_exit_failure_with_output();
+	let stderr = String::from_utf8_lossy(&output.stderr);
+	assert!(stderr.contains("ref 'nonexistent' does not exist"), "unexpected output: {stderr}");
+	assert!(!stderr.contains("--new"), "unexpected output: {stderr}");
+}
+
+#[test]
+fn reflog_message()
+{
+	let repo = TestRepo::new();
+
+	repo.commit("initial commit");
+	repo.branch("topic");
+	repo.commit("second commit");
+
+	let last_reflog = || {
+		let output = repo.git()
+			.args(["reflog", "show", "-1", "--format=%gs", "topic"])
+			.assert_spawn_exit_ok_with_output();
+		String::from_utf8_lossy(&output.stdout).trim().to_string()
+	};
+
+	repo.git_point()
+		.args(["topic", "main", "-m", "release prep"])
+		.assert_spawn_exit_ok();
+	let reflog = last_reflog();
+	assert!(reflog.starts_with("git-point: updating refs/heads/topic from "), "unexpected reflog: {reflog}");
+	assert!(reflog.ends_with(": release prep"), "unexpected reflog: {reflog}");
+
+	let mut cmd = repo.git_point().args(["topic", "main~"]);
+	cmd.command.env("GIT_POINT_REASON", "from a script");
+	cmd.assert_spawn_exit_ok();
+	assert!(last_reflog().ends_with(": from a script"), "unexpected reflog: {}", last_reflog());
+
+	repo.git()
+		.args(["config", "point.reflogMessage", "moved {ref} ({reason}) {unknown}"])
+		.assert_spawn_exit_ok();
+	repo.git_point()
+		.args(["topic", "main", "--message", "again"])
+		.assert_spawn_exit_ok();
+	assert_eq!(last_reflog(), "moved refs/heads/topic (again) {unknown}");
+}
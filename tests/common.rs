@@ -67,6 +67,31 @@ impl CommandWrapper
 		self
 	}
 
+	pub fn current_dir<P>(mut self, dir: P) -> Self
+	where
+		P: AsRef<Path>,
+	{
+		self.command.current_dir(dir);
+		self
+	}
+
+	pub fn env<K, V>(mut self, key: K, val: V) -> Self
+	where
+		K: AsRef<OsStr>,
+		V: AsRef<OsStr>,
+	{
+		self.command.env(key, val);
+		self
+	}
+
+	pub fn stdin<T>(mut self, cfg: T) -> Self
+	where
+		T: Into<Stdio>,
+	{
+		self.command.stdin(cfg);
+		self
+	}
+
 	pub fn assert_spawn(&mut self) -> ChildWrapper
 	{
 		let args: Vec<String> = self.command
@@ -103,6 +128,17 @@ impl CommandWrapper
 
 		child.assert_exit_ok_with_output()
 	}
+
+	/// Like [Self::assert_spawn_exit_ok_with_output], but asserts the command *fails*.
+	pub fn assert_spawn_exit_failure_with_output(mut self) -> Output
+	{
+		self.command.stdout(Stdio::piped());
+		self.command.stderr(Stdio::piped());
+
+		let child = self.assert_spawn();
+
+		child.assert_exit_failure_with_output()
+	}
 }
 
 #[derive(Debug)]
@@ -155,4 +191,26 @@ impl ChildWrapper
 
 		output
 	}
+
+	/// Does *not* setup stdout and stderr piping beforehand. You have to do that yourself.
+	pub fn assert_exit_failure_with_output(self) -> Output
+	{
+		let output = self.child.wait_with_output().unwrap_or_else(|e| {
+			panic!(
+				"error waiting for command '{} {}': {e} (killed by signal?)",
+				self.name,
+				join_words(self.args.iter()),
+			);
+		});
+
+		if output.status.success() {
+			panic!(
+				"command '{} {}' unexpectedly succeeded",
+				self.name,
+				join_words(self.args.iter()),
+			);
+		}
+
+		output
+	}
 }